@@ -0,0 +1,16 @@
+// desktop/src-tauri/src/bin/gen_openapi.rs
+
+use vrcp_lib::modules::server::openapi;
+
+const OUTPUT_PATH: &str = "../src/generated/openapi.json";
+
+fn main() {
+    println!("🚀 Generating OpenAPI document...");
+
+    let json = openapi()
+        .to_pretty_json()
+        .expect("Failed to serialize OpenAPI document");
+    std::fs::write(OUTPUT_PATH, json + "\n").expect("Failed to write OpenAPI document");
+
+    println!("✅ OpenAPI document generated at {}", OUTPUT_PATH);
+}
@@ -7,8 +7,8 @@ use std::path::Path;
 // ※もし "vrcp_lib" など別の名前ならそれに合わせてください
 // ※ "src-tauri" フォルダ内で作業している場合、通常プロジェクト名はフォルダ名と同じか、Cargo.tomlの package.name です。
 // ここでは仮に "app" としていますが、エラーが出る場合は Cargo.toml の name を確認して書き換えてください。
-use vrcp_lib::modules::db::LogDatabase;
-use vrcp_lib::modules::watcher::parse_log_line;
+use vrcp_lib::modules::db::{InsertSummary, LogDatabase};
+use vrcp_lib::modules::watcher::{parse_log_line, LogSource};
 
 fn main() {
     // 1. 引数の取得
@@ -32,7 +32,7 @@ fn main() {
     };
 
     // 3. ファイルごとの処理
-    let mut total_imported = 0;
+    let mut total = InsertSummary::default();
 
     for filename in &args[1..] {
         let path = Path::new(filename);
@@ -43,34 +43,60 @@ fn main() {
 
         println!("Processing: {:?}", path);
         match process_file(path, &db) {
-            Ok(count) => {
-                println!("  -> Imported {} lines.", count);
-                total_imported += count;
+            Ok(summary) => {
+                println!(
+                    "  -> Imported {} events, skipped {} duplicates.",
+                    summary.inserted, summary.skipped
+                );
+                total.inserted += summary.inserted;
+                total.skipped += summary.skipped;
             }
             Err(e) => eprintln!("  -> Error processing file: {}", e),
         }
     }
 
-    println!("Done! Total imported lines: {}", total_imported);
+    println!(
+        "Done! Total imported: {}, skipped (already in DB): {}",
+        total.inserted, total.skipped
+    );
 }
 
-fn process_file(path: &Path, db: &LogDatabase) -> Result<usize, Box<dyn std::error::Error>> {
+fn process_file(
+    path: &Path,
+    db: &LogDatabase,
+) -> Result<InsertSummary, Box<dyn std::error::Error>> {
     let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut count = 0;
+    let mut reader = BufReader::new(file);
+    let mut summary = InsertSummary::default();
 
-    // トランザクションを使うと高速ですが、今回はシンプルに1行ずつ処理
-    // 必要なら db.conn.lock().unwrap().transaction() ... を実装してください
+    // watcherと同じ取り込み元 (ファイル名 + 行の開始オフセット) を記録し、
+    // 同じファイルを何度取り込んでも重複しないようにする
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut offset: u64 = 0;
+    let mut buf = Vec::new();
 
-    for line_result in reader.lines() {
-        let line = line_result?;
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let line_offset = offset;
+        offset += read as u64;
+        let line = String::from_utf8_lossy(&buf);
 
         // watcherのリファクタリングした関数を使用
         if let Some(payload) = parse_log_line(&line) {
-            // 重複チェックはDB側のUNIQUE制約(INSERT OR IGNORE等)や
-            // insert_logの実装に任せる (エラーが出ても止まらないようにする)
-            match db.insert_log(&payload) {
-                Ok(_) => count += 1,
+            let source = LogSource {
+                file: file_name.clone(),
+                offset: line_offset,
+            };
+            // 重複はDB側のUNIQUE制約で無視される (エラーが出ても止まらないようにする)
+            match db.insert_log(&payload, Some(&source)) {
+                Ok(outcome) => summary.add(outcome),
                 Err(e) => {
                     eprintln!("Insert error: {}", e);
                 }
@@ -78,5 +104,5 @@ fn process_file(path: &Path, db: &LogDatabase) -> Result<usize, Box<dyn std::err
         }
     }
 
-    Ok(count)
+    Ok(summary)
 }
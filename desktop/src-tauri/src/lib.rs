@@ -1,5 +1,7 @@
 pub mod modules;
+use modules::migrations::SchemaTooNewError;
 use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tauri_specta::{collect_commands, collect_events, Builder as SpectaBuilder};
 
 // ---------------------------------------------------------
@@ -12,14 +14,38 @@ pub fn create_specta_builder() -> SpectaBuilder {
             modules::server::set_server_port,
             modules::server::get_server_port,
             modules::server::get_server_url,
+            modules::server::get_tls_enabled,
+            modules::server::set_tls_enabled,
+            modules::server::get_certificate_fingerprint,
+            modules::server::get_bind_mode,
+            modules::server::set_bind_mode,
+            modules::network::list_network_addresses,
+            modules::network::get_advertised_address,
+            modules::network::set_advertised_address,
+            modules::forwarder::get_forward_config,
+            modules::forwarder::set_forward_config,
+            modules::forwarder::get_forward_status,
+            modules::auth::get_pairing_info,
+            modules::auth::rotate_pairing_token,
+            modules::auth::revoke_pairing_token,
             modules::db::get_logs,
+            modules::db::get_my_avatars,
+            modules::db::get_player_avatars,
+            modules::db::get_world_hosts,
+            modules::db::get_screenshots,
+            modules::screenshot::get_embed_screenshot_metadata,
+            modules::screenshot::set_embed_screenshot_metadata,
+            modules::screenshot::import_screenshots,
             modules::db::delete_all_logs,
             modules::db::export_logs,
+            modules::db::export_watch_list,
         ])
         .events(collect_events![
             modules::watcher::Payload,
-            modules::watcher::VrcLogEvent
+            modules::watcher::VrcLogEvent,
+            modules::backfill::BackfillProgress,
         ])
+        .typ::<modules::watcher::VrcLogEventKind>()
 }
 
 // ---------------------------------------------------------
@@ -42,19 +68,57 @@ pub fn run() {
         .setup(move |app| {
             builder.mount_events(app);
 
+            let app_dir = app
+                .handle()
+                .path()
+                .app_local_data_dir()
+                .expect("failed to resolve app local data dir");
             // DB 初期化
-            let db = modules::db::LogDatabase::new(
-                app.handle()
-                    .path()
-                    .app_local_data_dir()
-                    .expect("failed to resolve app local data dir"),
-            )
-            .expect("failed to initialize database");
+            let db = match modules::db::LogDatabase::new(app_dir.clone()) {
+                Ok(db) => db,
+                Err(e) => {
+                    // 新しいバージョンのDBなど、開けない場合はダイアログで通知して終了する
+                    eprintln!("Failed to initialize database: {}", e);
+                    let message = match e.downcast_ref::<SchemaTooNewError>() {
+                        Some(too_new) => too_new.to_string(),
+                        None => format!("Failed to open the database.\n{}", e),
+                    };
+                    let handle = app.handle().clone();
+                    app.dialog()
+                        .message(message)
+                        .title("VRCP")
+                        .kind(MessageDialogKind::Error)
+                        .show(move |_| handle.exit(1));
+                    return Ok(());
+                }
+            };
             app.manage(db.clone());
-            // Watcher起動
-            modules::watcher::spawn_log_watcher(app.handle().clone(), db.clone());
+            // 別のPCへの転送 (watcher から参照するので先に登録する)
+            let forwarder =
+                modules::forwarder::Forwarder::new(db.clone(), app_dir.join("forward_spool.jsonl"));
+            forwarder.spawn();
+            app.manage(forwarder);
+            // ライブ配信用のイベントハブ
+            let hub = modules::hub::EventHub::new();
+            // Watcher起動 (監視状態は HTTPサーバーからも参照する)
+            let monitor = modules::watcher::WatcherMonitor::new();
+            modules::watcher::spawn_log_watcher(
+                app.handle().clone(),
+                db.clone(),
+                hub.clone(),
+                monitor.clone(),
+            );
+            // HTTPS 用の自己署名証明書 (作れなければ HTTP で起動する)
+            let tls = match modules::tls::TlsIdentity::load_or_create(&app_dir) {
+                Ok(tls) => Some(tls),
+                Err(e) => {
+                    eprintln!("Failed to prepare TLS certificate: {}", e);
+                    None
+                }
+            };
             // http srv 起動
-            modules::server::spawn_server(db);
+            let server = modules::server::spawn_server(db, hub, monitor, tls);
+            app.manage(server);
             // 常駐化設定
             modules::systray::setup_tray(app.handle())?;
 
@@ -0,0 +1,144 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use specta::Type;
+
+use super::db::LogDatabase;
+use super::server::ServerHandle;
+
+/// ペアリングトークンを保存する settings のキー (空文字 = 無効化済み)
+const TOKEN_KEY: &str = "auth_token";
+/// トークンのバイト数 (hex で 64 文字)
+const TOKEN_BYTES: usize = 32;
+
+/// モバイルアプリとのペアリング情報
+#[derive(Clone, Serialize, Type)]
+pub struct PairingInfo {
+    /// 接続先のURL
+    pub url: String,
+    /// Bearer トークン (無効化されている場合は null)
+    pub token: Option<String>,
+    /// サーバー証明書の SHA-256 フィンガープリント (HTTPS の場合)
+    pub fingerprint: Option<String>,
+    /// QRコードに埋め込む文字列 (vrcp://pair?url=...&token=...&fp=...)
+    pub qr_payload: Option<String>,
+}
+
+/// ランダムなトークンを生成する
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 現在有効なトークンを取得 (無効化されていれば None)
+pub fn current_token(db: &LogDatabase) -> Option<String> {
+    db.get_setting(TOKEN_KEY).ok().filter(|t| !t.is_empty())
+}
+
+/// 初回起動時にインストールごとのトークンを発行する
+/// (無効化済みの場合は再発行しない)
+pub fn ensure_token(db: &LogDatabase) -> Result<(), String> {
+    if db.get_setting(TOKEN_KEY).is_err() {
+        let token = generate_token()?;
+        db.set_setting(TOKEN_KEY, &token)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 長さに依存しない時間で比較する (タイミング攻撃対策)
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// リクエストからトークンを取り出す
+/// `Authorization: Bearer <token>` を優先し、ヘッダーを付けられない
+/// EventSource / WebSocket のために `?token=` クエリも受け付ける
+fn extract_token(req: &Request) -> Option<String> {
+    if let Some(value) = req.headers().get(header::AUTHORIZATION) {
+        return value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string());
+    }
+    req.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    })
+}
+
+/// 全ルートに適用する認証ミドルウェア
+pub async fn require_token(State(db): State<LogDatabase>, req: Request, next: Next) -> Response {
+    let Some(expected) = current_token(&db) else {
+        // トークンが無効化されている間は全て拒否
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    match extract_token(&req) {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            next.run(req).await
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+        )
+            .into_response(),
+    }
+}
+
+/// ペアリング情報を組み立てる
+fn pairing_info(server: &ServerHandle) -> Result<PairingInfo, String> {
+    let url = server.url()?;
+    let token = current_token(server.db());
+    let fingerprint = server.fingerprint();
+    let qr_payload = token.as_ref().map(|token| {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("url", &url).append_pair("token", token);
+        if let Some(fp) = &fingerprint {
+            query.append_pair("fp", fp);
+        }
+        format!("vrcp://pair?{}", query.finish())
+    });
+    Ok(PairingInfo {
+        url,
+        token,
+        fingerprint,
+        qr_payload,
+    })
+}
+
+// commands
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_pairing_info(server: tauri::State<'_, ServerHandle>) -> Result<PairingInfo, String> {
+    pairing_info(&server)
+}
+
+/// トークンを再発行する (ペアリング済みの端末は再ペアリングが必要になる)
+#[tauri::command]
+#[specta::specta]
+pub fn rotate_pairing_token(server: tauri::State<'_, ServerHandle>) -> Result<PairingInfo, String> {
+    let token = generate_token()?;
+    server
+        .db()
+        .set_setting(TOKEN_KEY, &token)
+        .map_err(|e| e.to_string())?;
+    pairing_info(&server)
+}
+
+/// トークンを無効化する (再発行するまで全ての端末からのアクセスを拒否する)
+#[tauri::command]
+#[specta::specta]
+pub fn revoke_pairing_token(db: tauri::State<'_, LogDatabase>) -> Result<(), String> {
+    db.set_setting(TOKEN_KEY, "").map_err(|e| e.to_string())
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+use crate::modules::db::{InsertOutcome, LogDatabase};
+use crate::modules::forwarder::{ForwardMode, Forwarder};
+use crate::modules::metrics::metrics;
+use crate::modules::screenshot;
+use crate::modules::watcher::{list_log_files, LogSource, Payload, TailedLogFile, VrcLogEvent};
+
+/// 1回のトランザクションでまとめて保存する件数
+const BATCH_SIZE: usize = 500;
+
+/// 過去ログ取り込みの進捗 (frontend へ通知)
+#[derive(Clone, Serialize, Deserialize, Type, Event)]
+pub struct BackfillProgress {
+    /// 処理中のファイル名
+    pub current_file: Option<String>,
+    pub files_done: u32,
+    pub files_total: u32,
+    /// 取り込んだイベント数 (累計)
+    pub events_imported: u32,
+    /// 取り込み済みだったためスキップしたイベント数 (累計)
+    pub events_skipped: u32,
+    pub finished: bool,
+}
+
+/// VRChatのログディレクトリにある全ログファイルを古い順に取り込む
+/// 初回は全ログを、以降はチェックポイント以降 (アプリ停止中に書かれた分) だけを読む
+/// 各ファイルのチェックポイントを更新するので、中断されても続きから再開できる
+pub fn run_backfill(app: &AppHandle, db: &LogDatabase) {
+    let files = list_log_files();
+    let mut progress = BackfillProgress {
+        current_file: None,
+        files_done: 0,
+        files_total: files.len() as u32,
+        events_imported: 0,
+        events_skipped: 0,
+        finished: false,
+    };
+
+    for path in &files {
+        progress.current_file = path.file_name().map(|n| n.to_string_lossy().to_string());
+        emit_progress(app, &progress);
+
+        if let Err(e) = backfill_file(path, db, app, &mut progress) {
+            eprintln!("Failed to backfill {:?}: {}", path, e);
+        }
+        progress.files_done += 1;
+    }
+
+    progress.current_file = None;
+    progress.finished = true;
+    emit_progress(app, &progress);
+    println!(
+        "Backfill finished: {} events from {} files.",
+        progress.events_imported, progress.files_done
+    );
+}
+
+/// 1ファイル分をチェックポイントの位置から末尾まで取り込む
+fn backfill_file(
+    path: &Path,
+    db: &LogDatabase,
+    app: &AppHandle,
+    progress: &mut BackfillProgress,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = TailedLogFile::open(path, db)?;
+    let mut batch: Vec<(Payload, LogSource)> = Vec::with_capacity(BATCH_SIZE);
+
+    while let Some((offset, line)) = file.next_line()? {
+        metrics().line_read();
+        if let Some(payload) = file.parse(&line) {
+            metrics().event_matched(payload.event.kind());
+            batch.push((payload, file.source_at(offset)));
+        }
+        if batch.len() >= BATCH_SIZE {
+            flush_batch(app, db, &mut batch, progress)?;
+            // 保存済みの位置までチェックポイントを進める
+            file.save_if_dirty(db);
+            emit_progress(app, progress);
+        }
+    }
+    if !batch.is_empty() {
+        flush_batch(app, db, &mut batch, progress)?;
+    }
+    file.save_if_dirty(db);
+    Ok(())
+}
+
+/// 溜まったイベントを保存 (転送) して進捗に反映する
+fn flush_batch(
+    app: &AppHandle,
+    db: &LogDatabase,
+    batch: &mut Vec<(Payload, LogSource)>,
+    progress: &mut BackfillProgress,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let forwarder = app.try_state::<Forwarder>();
+    let mode = forwarder.as_ref().map_or(ForwardMode::Off, |f| f.mode());
+    if mode == ForwardMode::ForwardOnly {
+        progress.events_imported += batch.len() as u32;
+    } else {
+        let outcomes = db.insert_logs(batch)?;
+        for ((payload, _), outcome) in batch.iter().zip(outcomes) {
+            match outcome {
+                InsertOutcome::Inserted(id) => {
+                    progress.events_imported += 1;
+                    // アプリを起動していない間に撮った写真にも書き込む
+                    if let VrcLogEvent::Screenshot { .. } = payload.event {
+                        screenshot::embed_existing(db, id);
+                    }
+                }
+                InsertOutcome::Skipped => progress.events_skipped += 1,
+            }
+        }
+    }
+    // 重複は転送先で取り除かれる
+    if let Some(forwarder) = forwarder {
+        forwarder.forward(batch.iter().map(|(payload, source)| (payload, source)));
+    }
+    batch.clear();
+    Ok(())
+}
+
+fn emit_progress(app: &AppHandle, progress: &BackfillProgress) {
+    if let Err(e) = progress.emit(app) {
+        eprintln!("Failed to emit backfill progress: {}", e);
+    }
+}
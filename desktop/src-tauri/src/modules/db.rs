@@ -1,15 +1,251 @@
-use super::watcher::{Payload, VrcLogEvent};
-use rusqlite::{params, Connection};
+use super::metrics::metrics;
+use super::migrations;
+use super::watcher::{LogSource, Payload, VrcLogEvent, VrcLogEventKind};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 use std::sync::{Arc, Mutex};
+use utoipa::ToSchema;
 
 // エラーハンドリング用
 type DbResult<T> = Result<T, Box<dyn std::error::Error>>;
 
+/// 既存のログを変更・削除した回数を保存する settings のキー (ETag 用)
+const LOG_GENERATION_KEY: &str = "log_generation";
+
+/// ログファイルごとの読み込み位置 (watcher の再開用)
+#[derive(Clone, Debug)]
+pub struct LogCheckpoint {
+    /// ログファイルのフルパス
+    pub path: String,
+    /// ファイルの同一性を判定するための識別子 (先頭行)
+    pub file_id: String,
+    /// 処理済みのバイトオフセット
+    pub offset: u64,
+}
+
+/// insert_log の結果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// 新規に保存した (保存した行の id)
+    Inserted(i64),
+    /// 同じイベントが保存済みだったため無視した
+    Skipped,
+}
+
+/// まとめて保存した際の件数
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InsertSummary {
+    pub inserted: usize,
+    pub skipped: usize,
+}
+
+impl InsertSummary {
+    /// 1件分の結果を集計に加える
+    pub fn add(&mut self, outcome: InsertOutcome) {
+        match outcome {
+            InsertOutcome::Inserted(_) => self.inserted += 1,
+            InsertOutcome::Skipped => self.skipped += 1,
+        }
+    }
+}
+
+/// get_logs の絞り込み条件 (指定された条件は全て AND で結合される)
+#[derive(Clone, Debug, Default, Deserialize, Type)]
+pub struct LogFilter {
+    /// 取得するイベントの種別 (未指定・空なら全種別)
+    pub kinds: Option<Vec<VrcLogEventKind>>,
+    /// プレイヤー / ログインユーザーの user_id (usr_xxx)
+    pub user_id: Option<String>,
+    /// ワールドID (wrld_xxx)
+    /// そのワールドに滞在中のイベント (同じログファイルで直前に参加したインスタンスのワールドで判定する)
+    pub world_id: Option<String>,
+    /// プレイヤー名・ユーザー名・ワールド名の部分一致
+    pub text: Option<String>,
+}
+
+impl LogFilter {
+    /// WHERE 句に条件を追加する
+    fn push_conditions(&self, sql: &mut String, args: &mut Vec<Value>) {
+        if let Some(kinds) = self.kinds.as_ref().filter(|k| !k.is_empty()) {
+            let placeholders = vec!["?"; kinds.len()].join(", ");
+            sql.push_str(&format!(" AND event_type IN ({})", placeholders));
+            args.extend(kinds.iter().map(|k| Value::from(k.as_str().to_string())));
+        }
+        if let Some(user_id) = &self.user_id {
+            sql.push_str(" AND json_extract(data, '$.data.user_id') = ?");
+            args.push(Value::from(user_id.clone()));
+        }
+        if let Some(world_id) = &self.world_id {
+            // 取り込み元が不明な行はどのセッションのものか分からないので、行自体の world_id で判定する
+            // (呼び出し元のクエリは logs をエイリアスなしで参照していること)
+            sql.push_str(
+                " AND CASE WHEN logs.source_file = '' THEN json_extract(logs.data, '$.data.world_id') ELSE (
+                     SELECT json_extract(i.data, '$.data.world_id') FROM logs i
+                     WHERE i.event_type = 'InstanceJoin'
+                       AND i.machine_id = logs.machine_id AND i.source_file = logs.source_file
+                       AND i.timestamp <= logs.timestamp
+                       AND (i.timestamp, i.source_offset) <= (logs.timestamp, logs.source_offset)
+                     ORDER BY i.timestamp DESC, i.source_offset DESC
+                     LIMIT 1
+                 ) END = ?",
+            );
+            args.push(Value::from(world_id.clone()));
+        }
+        if let Some(text) = self.text.as_ref().filter(|t| !t.is_empty()) {
+            sql.push_str(
+                " AND (json_extract(data, '$.data.player_name') LIKE ? ESCAPE '\\'
+                   OR json_extract(data, '$.data.username') LIKE ? ESCAPE '\\'
+                   OR json_extract(data, '$.data.world_name') LIKE ? ESCAPE '\\')",
+            );
+            let pattern = like_pattern(text);
+            for _ in 0..3 {
+                args.push(Value::from(pattern.clone()));
+            }
+        }
+    }
+}
+
+/// LIKE の部分一致パターンを作る (%, _ はエスケープする)
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// get_logs の1ページ分の結果
+#[derive(Clone, Serialize, Type, ToSchema)]
+pub struct LogPage {
+    pub items: Vec<Payload>,
+    /// 次のページを取得するためのカーソル (最後のページなら null)
+    pub next_cursor: Option<String>,
+}
+
+/// DBの状態 (/info 用)
+#[derive(Clone, Serialize, ToSchema)]
+pub struct DbStats {
+    /// 適用済みのスキーマバージョン (PRAGMA user_version)
+    pub schema_version: u32,
+    /// logs テーブルの総行数
+    pub total_logs: i64,
+    /// イベント種別ごとの行数
+    pub logs_by_type: BTreeMap<String, i64>,
+    /// 読み込み位置を記録しているログファイル数
+    pub tracked_files: i64,
+}
+
+/// 自分が着用したアバターの集計 (get_my_avatars)
+#[derive(Clone, Serialize, Type)]
+pub struct AvatarUsage {
+    pub avatar_name: String,
+    /// 期間内にこのアバターへ変更した回数
+    pub times: u32,
+    pub first_worn: String,
+    pub last_worn: String,
+}
+
+/// プレイヤーと会ったとき (PlayerJoin) に着用していたアバター (get_player_avatars)
+#[derive(Clone, Serialize, Type)]
+pub struct AvatarEncounter {
+    /// PlayerJoin の時刻
+    pub met_at: String,
+    pub player_name: String,
+    /// 退出するまでにアバターの読み込みが記録されていなければ null
+    pub avatar_name: Option<String>,
+}
+
+/// 写真に写っている (撮影時にインスタンスにいた) プレイヤー
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+pub struct ScreenshotPlayer {
+    pub user_id: String,
+    pub player_name: String,
+}
+
+/// 撮影した写真と撮影時の状況 (get_screenshots)
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct ScreenshotInfo {
+    pub timestamp: String,
+    /// 保存先のフルパス
+    pub path: String,
+    pub world_id: Option<String>,
+    pub world_name: Option<String>,
+    pub instance_id: Option<String>,
+    pub players: Vec<ScreenshotPlayer>,
+}
+
+/// ワールドが外部データを読み込んだホストの集計 (get_world_hosts)
+#[derive(Clone, Serialize, Type)]
+pub struct WorldHostUsage {
+    /// 読み込んだときにいたワールド (インスタンス参加が記録されていなければ null)
+    pub world_id: Option<String>,
+    pub world_name: Option<String>,
+    /// URL のホスト名 (URL として解釈できなければ URL そのもの)
+    pub host: String,
+    /// 期間内に読み込んだ回数
+    pub loads: u32,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// 視聴した動画 (export_watch_list)
+#[derive(Clone, Serialize)]
+pub struct WatchedVideo {
+    pub timestamp: String,
+    /// 再生したときにいたワールド (インスタンス参加が記録されていなければ null)
+    pub world_id: Option<String>,
+    pub world_name: Option<String>,
+    pub url: String,
+    pub resolved_url: Option<String>,
+    pub requested_by: Option<String>,
+}
+
+/// 行 id 付きのログ (差分同期用)
+#[derive(Clone, Serialize, Deserialize, Type, ToSchema)]
+pub struct LogRecord {
+    /// logs テーブルの行 id (挿入順に単調増加し、再利用されない)
+    pub id: i64,
+    #[serde(flatten)]
+    pub payload: Payload,
+}
+
+/// ページングのカーソルを作る (中身は最後に返した行の id だが、クライアントからは不透明な文字列として扱う)
+fn encode_cursor(id: i64) -> String {
+    id.to_string()
+}
+
+/// カーソルを行 id に戻す
+fn decode_cursor(cursor: &str) -> DbResult<i64> {
+    cursor
+        .parse()
+        .map_err(|_| format!("invalid cursor: {}", cursor).into())
+}
+
+/// カーソルが指す行が削除されていて、続きの位置が分からない
+#[derive(Debug)]
+pub struct ExpiredCursorError;
+
+impl std::fmt::Display for ExpiredCursorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The cursor has expired because the logs it points to were deleted. Please fetch from the first page again."
+        )
+    }
+}
+
+impl std::error::Error for ExpiredCursorError {}
+
 #[derive(Clone)]
 pub struct LogDatabase {
     conn: Arc<Mutex<Connection>>,
+    /// このPCの識別子 (settings の machine_id)
+    machine_id: Arc<str>,
 }
 
 impl LogDatabase {
@@ -25,30 +261,18 @@ impl LogDatabase {
         let db_path = app_dir.join("vrcp.db");
         println!("Database path: {:?}", db_path);
 
-        // 5. 接続とテーブル作成
-        let conn = Connection::open(db_path)?;
-
-        // log保存用table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS logs (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                timestamp TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                data TEXT NOT NULL
-            )",
-            [],
-        )?;
-        // setting用table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )",
+        // 5. 接続とスキーマのマイグレーション
+        let mut conn = Connection::open(db_path)?;
+        migrations::run_migrations(&mut conn, &app_dir)?;
+        let machine_id: String = conn.query_row(
+            "SELECT value FROM settings WHERE key = 'machine_id'",
             [],
+            |row| row.get(0),
         )?;
 
         Ok(LogDatabase {
             conn: Arc::new(Mutex::new(conn)),
+            machine_id: machine_id.into(),
         })
     }
 
@@ -70,54 +294,128 @@ impl LogDatabase {
         Ok(())
     }
 
-    //** Logs */
-    /// ログを1件保存する
-    pub fn insert_log(&self, payload: &Payload) -> DbResult<()> {
+    //** Checkpoints */
+    /// 指定ファイルの読み込み位置を取得 (未記録なら None)
+    pub fn get_checkpoint(&self, path: &str) -> DbResult<Option<LogCheckpoint>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT path, file_id, offset FROM log_checkpoints WHERE path = ?1")?;
+        let mut rows = stmt.query(params![path])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(LogCheckpoint {
+                path: row.get(0)?,
+                file_id: row.get(1)?,
+                offset: row.get::<_, i64>(2)? as u64,
+            })),
+            None => Ok(None),
+        }
+    }
+    /// 読み込み位置を保存
+    pub fn save_checkpoint(&self, checkpoint: &LogCheckpoint) -> DbResult<()> {
         let conn = self.conn.lock().unwrap();
-
-        // JSON変換
-        let data_json = serde_json::to_string(&payload.event)?;
-
-        // イベントタイプ名を取得 (簡易実装)
-        let event_type = format!("{:?}", payload.event)
-            .split_whitespace()
-            .next()
-            .unwrap_or("Unknown")
-            .to_string()
-            .replace(" {", "")
-            .replace("}", "");
-
         conn.execute(
-            "INSERT INTO logs (timestamp, event_type, data) VALUES (?1, ?2, ?3)",
-            params![payload.timestamp, event_type, data_json],
+            "INSERT OR REPLACE INTO log_checkpoints (path, file_id, offset, updated_at)
+             VALUES (?1, ?2, ?3, datetime('now'))",
+            params![
+                checkpoint.path,
+                checkpoint.file_id,
+                checkpoint.offset as i64
+            ],
         )?;
-
         Ok(())
     }
 
+    //** Logs */
+    /// ログを1件保存する
+    /// 同じイベント (時刻・種別・内容・取り込み元が一致) が既にあれば保存せず Skipped を返す
+    pub fn insert_log(
+        &self,
+        payload: &Payload,
+        source: Option<&LogSource>,
+    ) -> DbResult<InsertOutcome> {
+        let conn = self.conn.lock().unwrap();
+        let result = insert_log_with(&conn, &self.machine_id, payload, source);
+        match &result {
+            Ok(outcome) => metrics().event_inserted(*outcome),
+            Err(_) => metrics().insert_failed(1),
+        }
+        result
+    }
+
+    /// 複数のログを1トランザクションでまとめて保存する (過去ログ取り込み用)
+    /// 結果は `entries` と同じ順に返す
+    pub fn insert_logs(&self, entries: &[(Payload, LogSource)]) -> DbResult<Vec<InsertOutcome>> {
+        let mut conn = self.conn.lock().unwrap();
+        // 失敗時はロールバックされるので、バッチ全体を失敗として数える
+        let outcomes = insert_batch(
+            &mut conn,
+            &self.machine_id,
+            entries
+                .iter()
+                .map(|(payload, source)| (payload, Some(source))),
+        )
+        .inspect_err(|_| metrics().insert_failed(entries.len() as u64))?;
+        for outcome in &outcomes {
+            metrics().event_inserted(*outcome);
+        }
+        Ok(outcomes)
+    }
+
     /// Retrieve logs newer than the specified timestamp.
     /// timestamp format: "YYYY-MM-DD HH:mm:ss" (converted from VRChat log format "YYYY.MM.DD HH:mm:ss")
+    /// Optionally narrowed down by `filter` (event kinds, user, world, text).
     ///
+    /// Results are paged by `limit` (None = all). Pass the returned `next_cursor`
+    /// as `cursor` to fetch the following page.
     pub fn get_logs(
         &self,
         start_timestamp: Option<&str>,
         end_timestamp: Option<&str>,
-    ) -> DbResult<Vec<Payload>> {
+        filter: &LogFilter,
+        limit: Option<u32>,
+        cursor: Option<&str>,
+    ) -> DbResult<LogPage> {
+        let after_id = cursor.map(decode_cursor).transpose()?;
         let conn = self.conn.lock().unwrap();
+        // カーソルの行の位置 (削除されていれば続きから返せない)
+        let after = match after_id {
+            Some(id) => {
+                let timestamp: Option<String> = conn
+                    .query_row("SELECT timestamp FROM logs WHERE id = ?1", [id], |row| {
+                        row.get(0)
+                    })
+                    .optional()?;
+                Some((timestamp.ok_or(ExpiredCursorError)?, id))
+            }
+            None => None,
+        };
 
         // Prepare the SQL query
-        // String comparison works for ISO-like dates (YYYY.MM.DD...)
-        let mut stmt = conn.prepare(
-            "SELECT timestamp, data FROM logs
-             WHERE timestamp > ?1 AND timestamp <= ?2
-             ORDER BY timestamp ASC, id ASC",
-        )?;
-        let start = start_timestamp.unwrap_or("1970-01-01 00:00:00");
-        let end = end_timestamp.unwrap_or("9999-12-31 23:59:59");
+        let (mut sql, mut args) = range_query(
+            "SELECT id, timestamp, data FROM logs",
+            start_timestamp,
+            end_timestamp,
+            filter,
+        );
+        if let Some((after_timestamp, after_id)) = after {
+            // 並び順 (timestamp, id) でカーソルの行より後ろ
+            sql.push_str(" AND (timestamp, id) > (?, ?)");
+            args.push(Value::from(after_timestamp));
+            args.push(Value::from(after_id));
+        }
+        sql.push_str(" ORDER BY timestamp ASC, id ASC");
+        if let Some(limit) = limit {
+            // 次のページの有無を判定するため1件多く取る
+            sql.push_str(" LIMIT ?");
+            args.push(Value::from(limit as i64 + 1));
+        }
+
+        let mut stmt = conn.prepare(&sql)?;
         // Map the rows to Payload objects
-        let log_iter = stmt.query_map(params![start, end], |row| {
-            let timestamp: String = row.get(0)?;
-            let data_json: String = row.get(1)?;
+        let log_iter = stmt.query_map(params_from_iter(args), |row| {
+            let id: i64 = row.get(0)?;
+            let timestamp: String = row.get(1)?;
+            let data_json: String = row.get(2)?;
 
             // Deserialize JSON string back to VrcLogEvent Enum
             // Note: Since we are inside a closure returning rusqlite::Result,
@@ -125,43 +423,901 @@ impl LogDatabase {
             let event: VrcLogEvent = serde_json::from_str(&data_json)
                 .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
 
-            Ok(Payload { event, timestamp })
+            Ok((id, Payload { event, timestamp }))
         })?;
 
         // Collect results into a Vec
-        let mut logs = Vec::new();
+        let mut rows = Vec::new();
         for log in log_iter {
-            logs.push(log?);
+            rows.push(log?);
+        }
+
+        let mut next_cursor = None;
+        if let Some(limit) = limit {
+            if rows.len() > limit as usize {
+                rows.truncate(limit as usize);
+                next_cursor = rows.last().map(|(id, _)| encode_cursor(*id));
+            }
+        }
+
+        Ok(LogPage {
+            items: rows.into_iter().map(|(_, payload)| payload).collect(),
+            next_cursor,
+        })
+    }
+
+    /// 別のPCから送られてきたログをまとめて保存する (結果は `entries` と同じ順)
+    pub fn ingest_logs(
+        &self,
+        machine_id: &str,
+        entries: &[(Payload, Option<LogSource>)],
+    ) -> DbResult<Vec<InsertOutcome>> {
+        let mut conn = self.conn.lock().unwrap();
+        let outcomes = insert_batch(
+            &mut conn,
+            machine_id,
+            entries
+                .iter()
+                .map(|(payload, source)| (payload, source.as_ref())),
+        )
+        .inspect_err(|_| metrics().insert_failed(entries.len() as u64))?;
+        for outcome in &outcomes {
+            metrics().event_inserted(*outcome);
+        }
+        Ok(outcomes)
+    }
+
+    /// このPCの識別子
+    pub fn machine_id(&self) -> &str {
+        &self.machine_id
+    }
+
+    /// ログ全体のバージョン (最も新しい行の id, 変更世代) (ETag 用)
+    /// 行の id は再利用されないので、追加されれば id が、既存の行を変更・削除すれば世代が変わる
+    pub fn log_version(&self) -> DbResult<(i64, i64)> {
+        let conn = self.conn.lock().unwrap();
+        let latest_id: i64 =
+            conn.query_row("SELECT COALESCE(MAX(id), 0) FROM logs", [], |row| {
+                row.get(0)
+            })?;
+        let generation: Option<i64> = conn
+            .query_row(
+                "SELECT CAST(value AS INTEGER) FROM settings WHERE key = ?1",
+                params![LOG_GENERATION_KEY],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok((latest_id, generation.unwrap_or(0)))
+    }
+
+    /// 指定 id より後に保存されたログを、保存された順に最大 `limit` 件取得する
+    pub fn get_logs_since(&self, since_id: i64, limit: u32) -> DbResult<Vec<LogRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, data FROM logs
+             WHERE id > ?1
+             ORDER BY id ASC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![since_id, limit], row_to_record)?;
+
+        let mut records = Vec::new();
+        for record in rows {
+            records.push(record?);
+        }
+        Ok(records)
+    }
+
+    /// 直近に保存されたログを最大 `limit` 件、保存された順に取得する (ライブ配信の再送用)
+    pub fn get_recent_logs(&self, filter: &LogFilter, limit: u32) -> DbResult<Vec<LogRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut sql = String::from("SELECT id, timestamp, data FROM logs WHERE 1 = 1");
+        let mut args = Vec::new();
+        filter.push_conditions(&mut sql, &mut args);
+        sql.push_str(" ORDER BY id DESC LIMIT ?");
+        args.push(Value::from(limit as i64));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), row_to_record)?;
+
+        let mut records = Vec::new();
+        for record in rows {
+            records.push(record?);
+        }
+        records.reverse();
+        Ok(records)
+    }
+
+    /// DBに問い合わせできるか確認する (/health 用、テーブルは読まない)
+    pub fn ping(&self) -> DbResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// スキーマバージョンと行数を取得する
+    pub fn stats(&self) -> DbResult<DbStats> {
+        let conn = self.conn.lock().unwrap();
+        let schema_version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let tracked_files: i64 =
+            conn.query_row("SELECT COUNT(*) FROM log_checkpoints", [], |row| row.get(0))?;
+
+        let mut stmt = conn.prepare("SELECT event_type, COUNT(*) FROM logs GROUP BY event_type")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut logs_by_type = BTreeMap::new();
+        for row in rows {
+            let (event_type, count): (String, i64) = row?;
+            logs_by_type.insert(event_type, count);
+        }
+
+        Ok(DbStats {
+            schema_version,
+            total_logs: logs_by_type.values().sum(),
+            logs_by_type,
+            tracked_files,
+        })
+    }
+
+    /// DBファイルのサイズ (バイト)
+    pub fn size_bytes(&self) -> DbResult<u64> {
+        let conn = self.conn.lock().unwrap();
+        let size: i64 = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(size as u64)
+    }
+
+    /// 保存されている中で最も新しいイベントの発生時刻
+    pub fn newest_event_time(&self) -> DbResult<Option<chrono::DateTime<chrono::Local>>> {
+        let conn = self.conn.lock().unwrap();
+        let newest: Option<String> =
+            conn.query_row("SELECT MAX(timestamp) FROM logs", [], |row| row.get(0))?;
+        // timestamp は VRChat のログに書かれたローカル時刻 (YYYY-MM-DD HH:MM:SS)
+        Ok(newest
+            .and_then(|t| chrono::NaiveDateTime::parse_from_str(&t, "%Y-%m-%d %H:%M:%S").ok())
+            .and_then(|t| t.and_local_timezone(chrono::Local).earliest()))
+    }
+
+    //** Avatars */
+    /// 期間内に自分 (Login したユーザー) が着用したアバターを、最後に着用した順に取得する
+    pub fn get_my_avatars(
+        &self,
+        start_timestamp: Option<&str>,
+        end_timestamp: Option<&str>,
+    ) -> DbResult<Vec<AvatarUsage>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT json_extract(data, '$.data.avatar_name') AS avatar_name,
+                    COUNT(*), MIN(timestamp), MAX(timestamp)
+             FROM logs
+             WHERE event_type = 'AvatarChange'
+               AND timestamp > ?1 AND timestamp <= ?2
+               AND json_extract(data, '$.data.user_id') IN (
+                   SELECT json_extract(data, '$.data.user_id') FROM logs WHERE event_type = 'Login'
+               )
+             GROUP BY avatar_name
+             ORDER BY MAX(timestamp) DESC",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                start_timestamp.unwrap_or("1970-01-01 00:00:00"),
+                end_timestamp.unwrap_or("9999-12-31 23:59:59")
+            ],
+            |row| {
+                Ok(AvatarUsage {
+                    avatar_name: row.get(0)?,
+                    times: row.get(1)?,
+                    first_worn: row.get(2)?,
+                    last_worn: row.get(3)?,
+                })
+            },
+        )?;
+
+        let mut usages = Vec::new();
+        for usage in rows {
+            usages.push(usage?);
         }
+        Ok(usages)
+    }
+
+    /// 期間内にプレイヤーと会うたびに、そのプレイヤーが着用していたアバターを新しい順に取得する
+    /// 入室直後に記録されるアバターの読み込みを、同じログファイル内で退出するまでの範囲から探す
+    /// (そのプレイヤーの入退室とアバター変更をログファイルごとに1回だけ走査する)
+    pub fn get_player_avatars(
+        &self,
+        user_id: &str,
+        start_timestamp: Option<&str>,
+        end_timestamp: Option<&str>,
+    ) -> DbResult<Vec<AvatarEncounter>> {
+        let end_timestamp = end_timestamp.unwrap_or("9999-12-31 23:59:59");
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT machine_id, source_file, event_type, timestamp,
+                    json_extract(data, '$.data.player_name'),
+                    json_extract(data, '$.data.avatar_name')
+             FROM logs
+             WHERE event_type IN ('PlayerJoin', 'PlayerLeft', 'AvatarChange')
+               AND json_extract(data, '$.data.user_id') = ?1
+               AND timestamp > ?2
+             ORDER BY machine_id, source_file, timestamp, source_offset, id",
+        )?;
+        let rows = stmt.query_map(
+            params![user_id, start_timestamp.unwrap_or("1970-01-01 00:00:00")],
+            |row| {
+                Ok((
+                    (row.get::<_, String>(0)?, row.get::<_, String>(1)?),
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                ))
+            },
+        )?;
 
-        Ok(logs)
+        let mut encounters: Vec<AvatarEncounter> = Vec::new();
+        // アバターを探している (退出していない) 出会いの、ログファイルと encounters 内の位置
+        let mut open: Option<((String, String), usize)> = None;
+        for row in rows {
+            let (file, event_type, timestamp, player_name, avatar_name) = row?;
+            if open
+                .as_ref()
+                .is_some_and(|(open_file, _)| *open_file != file)
+            {
+                open = None;
+            }
+            match event_type.as_str() {
+                "PlayerJoin" => {
+                    open = None;
+                    if timestamp.as_str() <= end_timestamp {
+                        encounters.push(AvatarEncounter {
+                            met_at: timestamp,
+                            player_name,
+                            avatar_name: None,
+                        });
+                        open = Some((file, encounters.len() - 1));
+                    }
+                }
+                "PlayerLeft" => open = None,
+                _ => {
+                    if let Some((_, index)) = open.take() {
+                        encounters[index].avatar_name = avatar_name;
+                    }
+                }
+            }
+        }
+
+        // 新しい順 (同じ時刻なら後に記録されたものが先)
+        encounters.reverse();
+        encounters.sort_by(|a, b| b.met_at.cmp(&a.met_at));
+        Ok(encounters)
+    }
+
+    //** Remote Content */
+    /// 期間内にワールドごとに外部データ (String / Image Download) を読み込んだホストを集計する
+    /// どのワールドで読み込んだかは、同じログファイル内で直前に参加したインスタンスから求める
+    pub fn get_world_hosts(
+        &self,
+        start_timestamp: Option<&str>,
+        end_timestamp: Option<&str>,
+    ) -> DbResult<Vec<WorldHostUsage>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT (
+                 SELECT json_extract(i.data, '$.data.world_id') FROM logs i
+                 WHERE i.event_type = 'InstanceJoin'
+                   AND i.machine_id = r.machine_id AND i.source_file = r.source_file
+                   AND (i.timestamp, i.source_offset) <= (r.timestamp, r.source_offset)
+                 ORDER BY i.timestamp DESC, i.source_offset DESC
+                 LIMIT 1
+             ), (
+                 SELECT json_extract(w.data, '$.data.world_name') FROM logs w
+                 WHERE w.event_type = 'WorldEnter'
+                   AND w.machine_id = r.machine_id AND w.source_file = r.source_file
+                   AND (w.timestamp, w.source_offset) <= (r.timestamp, r.source_offset)
+                 ORDER BY w.timestamp DESC, w.source_offset DESC
+                 LIMIT 1
+             ), json_extract(r.data, '$.data.url'), r.timestamp
+             FROM logs r
+             WHERE r.event_type = 'RemoteContentLoad'
+               AND r.timestamp > ?1 AND r.timestamp <= ?2
+             ORDER BY r.timestamp ASC, r.id ASC",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                start_timestamp.unwrap_or("1970-01-01 00:00:00"),
+                end_timestamp.unwrap_or("9999-12-31 23:59:59")
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            },
+        )?;
+
+        // (world_id, host) ごとに集計する (ワールド名は最後に見たもの)
+        let mut usages: BTreeMap<(Option<String>, String), WorldHostUsage> = BTreeMap::new();
+        for row in rows {
+            let (world_id, world_name, url, timestamp) = row?;
+            let host = url::Url::parse(&url)
+                .ok()
+                .and_then(|u| u.host_str().map(str::to_string))
+                .unwrap_or(url);
+            let usage = usages
+                .entry((world_id.clone(), host.clone()))
+                .or_insert_with(|| WorldHostUsage {
+                    world_id,
+                    world_name: None,
+                    host,
+                    loads: 0,
+                    first_seen: timestamp.clone(),
+                    last_seen: timestamp.clone(),
+                });
+            usage.loads += 1;
+            usage.last_seen = timestamp;
+            if world_name.is_some() {
+                usage.world_name = world_name;
+            }
+        }
+
+        // ワールドごとに、読み込み回数の多い順
+        let mut usages: Vec<WorldHostUsage> = usages.into_values().collect();
+        usages.sort_by(|a, b| a.world_id.cmp(&b.world_id).then(b.loads.cmp(&a.loads)));
+        Ok(usages)
+    }
+
+    //** Videos */
+    /// 期間内に再生した動画を古い順に取得する
+    /// 1回の再生が複数行に分かれて記録されている場合 (以前のバージョンのログ) は1件にまとめる
+    pub fn get_watch_list(
+        &self,
+        start_timestamp: Option<&str>,
+        end_timestamp: Option<&str>,
+    ) -> DbResult<Vec<WatchedVideo>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT v.machine_id, v.source_file, v.timestamp, (
+                 SELECT json_extract(i.data, '$.data.world_id') FROM logs i
+                 WHERE i.event_type = 'InstanceJoin'
+                   AND i.machine_id = v.machine_id AND i.source_file = v.source_file
+                   AND (i.timestamp, i.source_offset) <= (v.timestamp, v.source_offset)
+                 ORDER BY i.timestamp DESC, i.source_offset DESC
+                 LIMIT 1
+             ), (
+                 SELECT json_extract(w.data, '$.data.world_name') FROM logs w
+                 WHERE w.event_type = 'WorldEnter'
+                   AND w.machine_id = v.machine_id AND w.source_file = v.source_file
+                   AND (w.timestamp, w.source_offset) <= (v.timestamp, v.source_offset)
+                 ORDER BY w.timestamp DESC, w.source_offset DESC
+                 LIMIT 1
+             ),
+             json_extract(v.data, '$.data.url'),
+             json_extract(v.data, '$.data.resolved_url'),
+             json_extract(v.data, '$.data.requested_by')
+             FROM logs v
+             WHERE v.event_type = 'VideoPlay'
+               AND v.timestamp > ?1 AND v.timestamp <= ?2
+             ORDER BY v.timestamp ASC, v.source_offset ASC, v.id ASC",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                start_timestamp.unwrap_or("1970-01-01 00:00:00"),
+                end_timestamp.unwrap_or("9999-12-31 23:59:59")
+            ],
+            |row| {
+                Ok((
+                    (row.get::<_, String>(0)?, row.get::<_, String>(1)?),
+                    WatchedVideo {
+                        timestamp: row.get(2)?,
+                        world_id: row.get(3)?,
+                        world_name: row.get(4)?,
+                        url: row.get(5)?,
+                        resolved_url: row.get(6)?,
+                        requested_by: row.get(7)?,
+                    },
+                ))
+            },
+        )?;
+
+        let mut videos: Vec<WatchedVideo> = Vec::new();
+        let mut last_file: Option<(String, String)> = None;
+        for row in rows {
+            let (file, video) = row?;
+            // 同じログファイルで直前と同じ URL (解決前・解決後) なら同じ再生
+            let same_playback = last_file.as_ref() == Some(&file)
+                && videos.last().is_some_and(|last| {
+                    last.url == video.url || last.resolved_url.as_ref() == Some(&video.url)
+                });
+            last_file = Some(file);
+            match videos.last_mut() {
+                Some(last) if same_playback => {
+                    last.resolved_url = last.resolved_url.take().or(video.resolved_url);
+                    last.requested_by = last.requested_by.take().or(video.requested_by);
+                }
+                _ => videos.push(video),
+            }
+        }
+        Ok(videos)
+    }
+
+    //** Screenshots */
+    /// 期間内に撮影した写真を古い順に取得する
+    /// `instance_id` を指定するとそのインスタンス (セッション) で、`user_id` を指定するとそのプレイヤーがいたときに撮影したものに絞り込む
+    pub fn get_screenshots(
+        &self,
+        start_timestamp: Option<&str>,
+        end_timestamp: Option<&str>,
+        instance_id: Option<&str>,
+        user_id: Option<&str>,
+    ) -> DbResult<Vec<ScreenshotInfo>> {
+        let conn = self.conn.lock().unwrap();
+        let mut sql = String::from(
+            "SELECT log_id, timestamp, path, world_id, world_name, instance_id FROM screenshots
+             WHERE timestamp > ? AND timestamp <= ?",
+        );
+        let mut args = vec![
+            Value::from(start_timestamp.unwrap_or("1970-01-01 00:00:00").to_string()),
+            Value::from(end_timestamp.unwrap_or("9999-12-31 23:59:59").to_string()),
+        ];
+        if let Some(instance_id) = instance_id {
+            sql.push_str(" AND instance_id = ?");
+            args.push(Value::from(instance_id.to_string()));
+        }
+        if let Some(user_id) = user_id {
+            sql.push_str(
+                " AND log_id IN (SELECT log_id FROM screenshot_players WHERE user_id = ?)",
+            );
+            args.push(Value::from(user_id.to_string()));
+        }
+        sql.push_str(" ORDER BY timestamp ASC, log_id ASC");
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                ScreenshotInfo {
+                    timestamp: row.get(1)?,
+                    path: row.get(2)?,
+                    world_id: row.get(3)?,
+                    world_name: row.get(4)?,
+                    instance_id: row.get(5)?,
+                    players: Vec::new(),
+                },
+            ))
+        })?;
+
+        let mut screenshots = Vec::new();
+        for row in rows {
+            let (log_id, mut screenshot) = row?;
+            screenshot.players = screenshot_players(&conn, log_id)?;
+            screenshots.push(screenshot);
+        }
+        Ok(screenshots)
+    }
+
+    /// Screenshot イベント (logs.id) の写真を取得する
+    pub fn get_screenshot(&self, log_id: i64) -> DbResult<Option<ScreenshotInfo>> {
+        let conn = self.conn.lock().unwrap();
+        let screenshot = conn
+            .query_row(
+                "SELECT timestamp, path, world_id, world_name, instance_id FROM screenshots
+                 WHERE log_id = ?1",
+                params![log_id],
+                |row| {
+                    Ok(ScreenshotInfo {
+                        timestamp: row.get(0)?,
+                        path: row.get(1)?,
+                        world_id: row.get(2)?,
+                        world_name: row.get(3)?,
+                        instance_id: row.get(4)?,
+                        players: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+        match screenshot {
+            Some(mut screenshot) => {
+                screenshot.players = screenshot_players(&conn, log_id)?;
+                Ok(Some(screenshot))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// PNG に埋め込まれていた撮影時の状況から写真を登録する
+    /// 同じパスの写真が登録済みなら何もせず false を返す
+    pub fn import_screenshot(&self, info: &ScreenshotInfo) -> DbResult<bool> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        // 写真は移動・コピーされることがあるので、パスではなく撮影時刻とファイル名で重複を判定する
+        // (VRChat のファイル名には撮影時刻がミリ秒まで含まれる)
+        let exists = {
+            let mut stmt = tx.prepare("SELECT path FROM screenshots WHERE timestamp = ?1")?;
+            let paths = stmt.query_map(params![info.timestamp], |row| row.get::<_, String>(0))?;
+            let mut exists = false;
+            for path in paths {
+                exists |= file_name(&path?).eq_ignore_ascii_case(file_name(&info.path));
+            }
+            exists
+        };
+        if exists {
+            return Ok(false);
+        }
+
+        let payload = Payload {
+            event: VrcLogEvent::Screenshot {
+                path: info.path.clone(),
+            },
+            timestamp: info.timestamp.clone(),
+        };
+        // 取り込み元のログがないので、状況は PNG の内容で上書きする
+        let InsertOutcome::Inserted(log_id) =
+            insert_log_with(&tx, &self.machine_id, &payload, None)?
+        else {
+            return Ok(false);
+        };
+        tx.execute(
+            "UPDATE screenshots SET world_id = ?2, world_name = ?3, instance_id = ?4
+             WHERE log_id = ?1",
+            params![log_id, info.world_id, info.world_name, info.instance_id],
+        )?;
+        for player in &info.players {
+            tx.execute(
+                "INSERT OR IGNORE INTO screenshot_players (log_id, user_id, player_name)
+                 VALUES (?1, ?2, ?3)",
+                params![log_id, player.user_id, player.player_name],
+            )?;
+        }
+        tx.commit()?;
+        metrics().event_inserted(InsertOutcome::Inserted(log_id));
+        Ok(true)
     }
 
     /// ログを全て削除し、DBのファイルサイズを最小化(VACUUM)する
     pub fn delete_all_logs(&self) -> DbResult<()> {
         let conn = self.conn.lock().unwrap();
         // 1. 全削除
-        conn.execute("DELETE FROM logs", [])?;
+        conn.execute_batch(
+            "DELETE FROM logs;
+             DELETE FROM screenshots;
+             DELETE FROM screenshot_players;",
+        )?;
+        bump_log_generation(&conn)?;
         // 2. 空き領域の解放 (ファイルサイズを小さくする)
         conn.execute("VACUUM", [])?;
         Ok(())
     }
 }
 
+/// 既存のログを変更・削除したことを記録する (ETag を変えるため)
+fn bump_log_generation(conn: &Connection) -> DbResult<()> {
+    conn.prepare_cached(
+        "INSERT INTO settings (key, value) VALUES (?1, '1')
+         ON CONFLICT(key) DO UPDATE SET value = CAST(value AS INTEGER) + 1",
+    )?
+    .execute(params![LOG_GENERATION_KEY])?;
+    Ok(())
+}
+
+/// 写真に写っているプレイヤーを名前順に取得する
+fn screenshot_players(conn: &Connection, log_id: i64) -> DbResult<Vec<ScreenshotPlayer>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT user_id, player_name FROM screenshot_players
+         WHERE log_id = ?1
+         ORDER BY player_name ASC",
+    )?;
+    let rows = stmt.query_map(params![log_id], |row| {
+        Ok(ScreenshotPlayer {
+            user_id: row.get(0)?,
+            player_name: row.get(1)?,
+        })
+    })?;
+
+    let mut players = Vec::new();
+    for player in rows {
+        players.push(player?);
+    }
+    Ok(players)
+}
+
+/// 1つのトランザクションでまとめて保存する
+fn insert_batch<'a>(
+    conn: &mut Connection,
+    machine_id: &str,
+    entries: impl Iterator<Item = (&'a Payload, Option<&'a LogSource>)>,
+) -> DbResult<Vec<InsertOutcome>> {
+    let tx = conn.transaction()?;
+    let mut outcomes = Vec::new();
+    for (payload, source) in entries {
+        outcomes.push(insert_log_with(&tx, machine_id, payload, source)?);
+    }
+    tx.commit()?;
+    Ok(outcomes)
+}
+
+/// 期間と絞り込み条件の WHERE 句を `select` に付けたクエリを作る
+fn range_query(
+    select: &str,
+    start_timestamp: Option<&str>,
+    end_timestamp: Option<&str>,
+    filter: &LogFilter,
+) -> (String, Vec<Value>) {
+    // String comparison works for ISO-like dates (YYYY.MM.DD...)
+    let start = start_timestamp.unwrap_or("1970-01-01 00:00:00");
+    let end = end_timestamp.unwrap_or("9999-12-31 23:59:59");
+    let mut sql = format!("{} WHERE timestamp > ? AND timestamp <= ?", select);
+    let mut args = vec![Value::from(start.to_string()), Value::from(end.to_string())];
+    filter.push_conditions(&mut sql, &mut args);
+    (sql, args)
+}
+
+/// `SELECT id, timestamp, data` の1行を LogRecord に変換する
+fn row_to_record(row: &rusqlite::Row) -> rusqlite::Result<LogRecord> {
+    let data_json: String = row.get(2)?;
+    let event: VrcLogEvent = serde_json::from_str(&data_json)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    Ok(LogRecord {
+        id: row.get(0)?,
+        payload: Payload {
+            event,
+            timestamp: row.get(1)?,
+        },
+    })
+}
+
+/// 1件保存の共通処理 (Connection / Transaction の両方から使う)
+fn insert_log_with(
+    conn: &Connection,
+    machine_id: &str,
+    payload: &Payload,
+    source: Option<&LogSource>,
+) -> DbResult<InsertOutcome> {
+    // JSON変換
+    let data_json = serde_json::to_string(&payload.event)?;
+    // 取り込み元が不明なイベントは空文字と -1 で保存する (NULL は UNIQUE 判定で区別されないため)
+    let (source_file, source_offset) = match source {
+        Some(src) => (src.file.as_str(), src.offset as i64),
+        None => ("", -1),
+    };
+
+    // 取り込み元を記録する前 (v3 より前) に保存された同じイベントがあれば、
+    // 新たに保存せずその行に取り込み元を記録する (アップグレード後の再読み込みで重複させないため)
+    if source.is_some() {
+        let adopted = conn
+            .prepare_cached(
+                "UPDATE OR IGNORE logs SET source_file = ?5, source_offset = ?6
+                 WHERE id = (
+                     SELECT id FROM logs
+                     WHERE machine_id = ?1 AND timestamp = ?2 AND event_type = ?3 AND data = ?4
+                       AND source_file = '' AND source_offset = -1
+                     LIMIT 1
+                 )",
+            )?
+            .execute(params![
+                machine_id,
+                payload.timestamp,
+                payload.event.kind().as_str(),
+                data_json,
+                source_file,
+                source_offset
+            ])?;
+        if adopted > 0 {
+            // 取り込み元が変わるとセッションの対応 (world_id での絞り込み) も変わる
+            bump_log_generation(conn)?;
+            return Ok(InsertOutcome::Skipped);
+        }
+    }
+
+    let changed = conn
+        .prepare_cached(
+            "INSERT OR IGNORE INTO logs (timestamp, event_type, data, source_file, source_offset, machine_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?
+        .execute(params![
+            payload.timestamp,
+            payload.event.kind().as_str(),
+            data_json,
+            source_file,
+            source_offset,
+            machine_id
+        ])?;
+
+    if changed == 0 {
+        return Ok(InsertOutcome::Skipped);
+    }
+    let id = conn.last_insert_rowid();
+    if let VrcLogEvent::Screenshot { path } = &payload.event {
+        record_screenshot(
+            conn,
+            id,
+            machine_id,
+            &payload.timestamp,
+            path,
+            source_file,
+            source_offset,
+        )?;
+    }
+    Ok(InsertOutcome::Inserted(id))
+}
+
+/// パスのファイル名部分 (Windows のパスも扱えるよう、どちらの区切り文字でも分割する)
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// 写真を撮影時の状況と共に screenshots / screenshot_players へ保存する
+/// 状況は同じログファイルの保存済みのイベント (撮影より前の行) から求めるので、
+/// 別のPCから転送されてきた写真も同じように扱える
+fn record_screenshot(
+    conn: &Connection,
+    log_id: i64,
+    machine_id: &str,
+    timestamp: &str,
+    path: &str,
+    source_file: &str,
+    source_offset: i64,
+) -> DbResult<()> {
+    // 取り込み元が不明な場合は、どのセッションで撮ったか分からない
+    if source_file.is_empty() {
+        conn.prepare_cached(
+            "INSERT OR IGNORE INTO screenshots (log_id, timestamp, path) VALUES (?1, ?2, ?3)",
+        )?
+        .execute(params![log_id, timestamp, path])?;
+        return Ok(());
+    }
+
+    // 撮影時点のインスタンス (最後に参加したもの) とワールド名
+    let instance: Option<(Option<String>, Option<String>, String, i64)> = conn
+        .prepare_cached(
+            "SELECT json_extract(data, '$.data.world_id'), json_extract(data, '$.data.instance_id'),
+                    timestamp, source_offset
+             FROM logs
+             WHERE event_type = 'InstanceJoin' AND machine_id = ?1 AND source_file = ?2
+               AND (timestamp, source_offset) <= (?3, ?4)
+             ORDER BY timestamp DESC, source_offset DESC
+             LIMIT 1",
+        )?
+        .query_row(
+            params![machine_id, source_file, timestamp, source_offset],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?;
+    let world_name: Option<String> = conn
+        .prepare_cached(
+            "SELECT json_extract(data, '$.data.world_name') FROM logs
+             WHERE event_type = 'WorldEnter' AND machine_id = ?1 AND source_file = ?2
+               AND (timestamp, source_offset) <= (?3, ?4)
+             ORDER BY timestamp DESC, source_offset DESC
+             LIMIT 1",
+        )?
+        .query_row(
+            params![machine_id, source_file, timestamp, source_offset],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+
+    let (world_id, instance_id, since) = match instance {
+        Some((world_id, instance_id, ts, offset)) => (world_id, instance_id, (ts, offset)),
+        // インスタンス参加が記録されていなければファイルの先頭から
+        None => (None, None, (String::new(), -1)),
+    };
+    conn.prepare_cached(
+        "INSERT OR IGNORE INTO screenshots (log_id, timestamp, path, world_id, world_name, instance_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?
+    .execute(params![log_id, timestamp, path, world_id, world_name, instance_id])?;
+
+    // インスタンス参加以降に入室し、撮影時点でまだ退出していないプレイヤー
+    conn.prepare_cached(
+        "INSERT OR IGNORE INTO screenshot_players (log_id, user_id, player_name)
+         SELECT ?1, json_extract(j.data, '$.data.user_id'), json_extract(j.data, '$.data.player_name')
+         FROM logs j
+         WHERE j.event_type = 'PlayerJoin' AND j.machine_id = ?2 AND j.source_file = ?3
+           AND (j.timestamp, j.source_offset) >= (?6, ?7)
+           AND (j.timestamp, j.source_offset) <= (?4, ?5)
+           AND NOT EXISTS (
+               SELECT 1 FROM logs l
+               WHERE l.event_type = 'PlayerLeft' AND l.machine_id = ?2 AND l.source_file = ?3
+                 AND json_extract(l.data, '$.data.user_id') = json_extract(j.data, '$.data.user_id')
+                 AND (l.timestamp, l.source_offset) > (j.timestamp, j.source_offset)
+                 AND (l.timestamp, l.source_offset) <= (?4, ?5)
+           )",
+    )?
+    .execute(params![
+        log_id,
+        machine_id,
+        source_file,
+        timestamp,
+        source_offset,
+        since.0,
+        since.1
+    ])?;
+    Ok(())
+}
+
 // commands
 
+/// export_logs で1回に読み込む件数
+const EXPORT_PAGE_SIZE: u32 = 1000;
+
 #[tauri::command]
 #[specta::specta]
 pub fn get_logs(
     db: tauri::State<'_, LogDatabase>,
     start: Option<String>,
     end: Option<String>,
-) -> Result<Vec<Payload>, String> {
+    filter: Option<LogFilter>,
+    limit: Option<u32>,
+    cursor: Option<String>,
+) -> Result<LogPage, String> {
     // db.get_logs の frontからの呼び出し
-    db.get_logs(start.as_deref(), end.as_deref())
+    db.get_logs(
+        start.as_deref(),
+        end.as_deref(),
+        &filter.unwrap_or_default(),
+        limit,
+        cursor.as_deref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_my_avatars(
+    db: tauri::State<'_, LogDatabase>,
+    start: Option<String>,
+    end: Option<String>,
+) -> Result<Vec<AvatarUsage>, String> {
+    db.get_my_avatars(start.as_deref(), end.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_player_avatars(
+    db: tauri::State<'_, LogDatabase>,
+    user_id: String,
+    start: Option<String>,
+    end: Option<String>,
+) -> Result<Vec<AvatarEncounter>, String> {
+    db.get_player_avatars(&user_id, start.as_deref(), end.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_world_hosts(
+    db: tauri::State<'_, LogDatabase>,
+    start: Option<String>,
+    end: Option<String>,
+) -> Result<Vec<WorldHostUsage>, String> {
+    db.get_world_hosts(start.as_deref(), end.as_deref())
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+#[specta::specta]
+pub fn get_screenshots(
+    db: tauri::State<'_, LogDatabase>,
+    start: Option<String>,
+    end: Option<String>,
+    instance_id: Option<String>,
+    user_id: Option<String>,
+) -> Result<Vec<ScreenshotInfo>, String> {
+    db.get_screenshots(
+        start.as_deref(),
+        end.as_deref(),
+        instance_id.as_deref(),
+        user_id.as_deref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 #[specta::specta]
 pub fn delete_all_logs(db: tauri::State<'_, LogDatabase>) -> Result<(), String> {
@@ -169,11 +1325,41 @@ pub fn delete_all_logs(db: tauri::State<'_, LogDatabase>) -> Result<(), String>
     Ok(())
 }
 
+/// 視聴した動画の一覧を JSON ファイルに書き出す
+#[tauri::command]
+#[specta::specta]
+pub fn export_watch_list(
+    db: tauri::State<'_, LogDatabase>,
+    file_path: String,
+) -> Result<usize, String> {
+    let videos = db.get_watch_list(None, None).map_err(|e| e.to_string())?;
+    let file = fs::File::create(file_path).map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(io::BufWriter::new(file), &videos).map_err(|e| e.to_string())?;
+    Ok(videos.len())
+}
+
 #[tauri::command]
 #[specta::specta]
 pub fn export_logs(db: tauri::State<'_, LogDatabase>, file_path: String) -> Result<usize, String> {
-    // 1. 全ログを取得 (since=None, until=None で全期間)
-    let logs = db.get_logs(None, None).map_err(|e| e.to_string())?;
+    // 1. 全ログを取得 (DBを長時間ロックしないようページ単位で読む)
+    let mut logs: Vec<Payload> = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page = db
+            .get_logs(
+                None,
+                None,
+                &LogFilter::default(),
+                Some(EXPORT_PAGE_SIZE),
+                cursor.as_deref(),
+            )
+            .map_err(|e| e.to_string())?;
+        logs.extend(page.items);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
     let count = logs.len();
 
     // 2. ファイルを作成
@@ -185,3 +1371,272 @@ pub fn export_logs(db: tauri::State<'_, LogDatabase>, file_path: String) -> Resu
 
     Ok(count)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(timestamp: &str, player_name: &str, user_id: &str) -> Payload {
+        Payload {
+            event: VrcLogEvent::PlayerJoin {
+                player_name: player_name.to_string(),
+                user_id: user_id.to_string(),
+            },
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    fn source(offset: u64) -> LogSource {
+        LogSource {
+            file: "output_log_2024-01-01_10-00-00.txt".to_string(),
+            offset,
+        }
+    }
+
+    fn count_logs(db: &LogDatabase) -> i64 {
+        db.stats().unwrap().total_logs
+    }
+
+    #[test]
+    fn legacy_rows_are_not_duplicated_by_backfill_after_upgrade() {
+        let dir = tempfile::tempdir().unwrap();
+        let event = payload("2024-01-01 10:00:01", "Alice", "usr_a");
+        {
+            // user_version も取り込み元もない頃の DB
+            let conn = Connection::open(dir.path().join("vrcp.db")).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE logs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    timestamp TEXT NOT NULL,
+                    event_type TEXT NOT NULL,
+                    data TEXT NOT NULL
+                );
+                CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);",
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO logs (timestamp, event_type, data) VALUES (?1, 'PlayerJoin', ?2)",
+                params![
+                    event.timestamp,
+                    serde_json::to_string(&event.event).unwrap()
+                ],
+            )
+            .unwrap();
+        }
+
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        assert_eq!(count_logs(&db), 1);
+
+        // 過去ログの再読み込みで同じイベントが取り込み元付きで来る
+        let outcomes = db
+            .insert_logs(&[
+                (event.clone(), source(100)),
+                (payload("2024-01-01 10:00:02", "Bob", "usr_b"), source(200)),
+            ])
+            .unwrap();
+        assert!(matches!(
+            outcomes[..],
+            [InsertOutcome::Skipped, InsertOutcome::Inserted(_)]
+        ));
+        assert_eq!(count_logs(&db), 2);
+
+        // 既存の行に取り込み元が記録され、以降は通常の重複として扱われる
+        assert_eq!(
+            db.insert_log(&event, Some(&source(100))).unwrap(),
+            InsertOutcome::Skipped
+        );
+        assert_eq!(count_logs(&db), 2);
+    }
+
+    #[test]
+    fn cursor_to_deleted_row_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        for (i, name) in ["Alice", "Bob", "Carol"].iter().enumerate() {
+            db.insert_log(
+                &payload(&format!("2024-01-01 10:00:0{}", i), name, "usr_x"),
+                Some(&source(i as u64 * 100)),
+            )
+            .unwrap();
+        }
+
+        let first = db
+            .get_logs(None, None, &LogFilter::default(), Some(2), None)
+            .unwrap();
+        let cursor = first.next_cursor.unwrap();
+        let rest = db
+            .get_logs(None, None, &LogFilter::default(), Some(2), Some(&cursor))
+            .unwrap();
+        assert_eq!(rest.items.len(), 1);
+        assert_eq!(rest.next_cursor, None);
+
+        db.delete_all_logs().unwrap();
+        let err = db
+            .get_logs(None, None, &LogFilter::default(), Some(2), Some(&cursor))
+            .err()
+            .unwrap();
+        assert!(err.is::<ExpiredCursorError>());
+    }
+
+    #[test]
+    fn watch_list_merges_lines_of_one_playback() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        let event = |timestamp: &str, event: VrcLogEvent| Payload {
+            event,
+            timestamp: timestamp.to_string(),
+        };
+        let video = |url: &str, resolved_url: Option<&str>| VrcLogEvent::VideoPlay {
+            url: url.to_string(),
+            resolved_url: resolved_url.map(str::to_string),
+            requested_by: None,
+        };
+        let rows = [
+            event(
+                "2024-01-01 10:00:00",
+                VrcLogEvent::InstanceJoin {
+                    world_id: "wrld_a".to_string(),
+                    instance_id: "1".to_string(),
+                },
+            ),
+            // 以前のバージョンは1回の再生を3行に分けて記録していた
+            event("2024-01-01 10:01:00", video("https://youtu.be/x", None)),
+            event(
+                "2024-01-01 10:01:01",
+                video("https://youtu.be/x", Some("https://cdn/x.mp4")),
+            ),
+            event("2024-01-01 10:01:01", video("https://cdn/x.mp4", None)),
+            event("2024-01-01 10:05:00", video("https://youtu.be/y", None)),
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            db.insert_log(row, Some(&source(i as u64 * 100))).unwrap();
+        }
+
+        let videos = db.get_watch_list(None, None).unwrap();
+        assert_eq!(videos.len(), 2);
+        assert_eq!(videos[0].url, "https://youtu.be/x");
+        assert_eq!(videos[0].resolved_url.as_deref(), Some("https://cdn/x.mp4"));
+        assert_eq!(videos[0].world_id.as_deref(), Some("wrld_a"));
+        assert_eq!(videos[1].url, "https://youtu.be/y");
+    }
+
+    #[test]
+    fn player_avatar_is_searched_until_the_player_leaves() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        let left = |timestamp: &str| Payload {
+            event: VrcLogEvent::PlayerLeft {
+                player_name: "Alice".to_string(),
+                user_id: "usr_a".to_string(),
+            },
+            timestamp: timestamp.to_string(),
+        };
+        let avatar = |timestamp: &str, avatar_name: &str| Payload {
+            event: VrcLogEvent::AvatarChange {
+                player_name: "Alice".to_string(),
+                user_id: Some("usr_a".to_string()),
+                avatar_name: avatar_name.to_string(),
+            },
+            timestamp: timestamp.to_string(),
+        };
+        let rows = [
+            payload("2024-01-01 10:00:00", "Alice", "usr_a"),
+            avatar("2024-01-01 10:00:01", "Robot"),
+            avatar("2024-01-01 10:00:30", "Cat"),
+            left("2024-01-01 10:01:00"),
+            // 2回目はアバターの読み込みが記録される前に退出した
+            payload("2024-01-01 10:02:00", "Alice", "usr_a"),
+            left("2024-01-01 10:03:00"),
+            avatar("2024-01-01 10:04:00", "Fox"),
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            db.insert_log(row, Some(&source(i as u64 * 100))).unwrap();
+        }
+
+        let encounters = db.get_player_avatars("usr_a", None, None).unwrap();
+        let avatars: Vec<(&str, Option<&str>)> = encounters
+            .iter()
+            .map(|e| (e.met_at.as_str(), e.avatar_name.as_deref()))
+            .collect();
+        assert_eq!(
+            avatars,
+            vec![
+                ("2024-01-01 10:02:00", None),
+                ("2024-01-01 10:00:00", Some("Robot")),
+            ]
+        );
+    }
+
+    #[test]
+    fn moved_screenshot_is_not_imported_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        let shot = Payload {
+            event: VrcLogEvent::Screenshot {
+                path: r"C:\Users\me\Pictures\VRChat\VRChat_2024-01-01_10-00-00.000_1920x1080.png"
+                    .to_string(),
+            },
+            timestamp: "2024-01-01 10:00:00".to_string(),
+        };
+        db.insert_log(&shot, Some(&source(0))).unwrap();
+
+        let imported = |name: &str| ScreenshotInfo {
+            timestamp: "2024-01-01 10:00:00".to_string(),
+            path: format!("D:/Backup/VRChat/{}", name),
+            world_id: None,
+            world_name: None,
+            instance_id: None,
+            players: Vec::new(),
+        };
+        // 別のフォルダへ移した同じ写真
+        assert!(!db
+            .import_screenshot(&imported("VRChat_2024-01-01_10-00-00.000_1920x1080.png"))
+            .unwrap());
+        // 同じ時刻に撮った別の写真
+        assert!(db
+            .import_screenshot(&imported("VRChat_2024-01-01_10-00-00.500_1920x1080.png"))
+            .unwrap());
+        assert_eq!(db.get_screenshots(None, None, None, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn log_version_changes_on_insert_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        let empty = db.log_version().unwrap();
+
+        db.insert_log(
+            &payload("2024-01-01 10:00:00", "Alice", "usr_a"),
+            Some(&source(0)),
+        )
+        .unwrap();
+        let inserted = db.log_version().unwrap();
+        assert_ne!(inserted, empty);
+
+        db.delete_all_logs().unwrap();
+        let deleted = db.log_version().unwrap();
+        assert_ne!(deleted, inserted);
+        assert_ne!(deleted, empty);
+    }
+
+    #[test]
+    fn same_event_from_another_line_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        let event = payload("2024-01-01 10:00:01", "Alice", "usr_a");
+
+        assert!(matches!(
+            db.insert_log(&event, Some(&source(100))).unwrap(),
+            InsertOutcome::Inserted(_)
+        ));
+        assert!(matches!(
+            db.insert_log(&event, Some(&source(200))).unwrap(),
+            InsertOutcome::Inserted(_)
+        ));
+        assert_eq!(
+            db.insert_log(&event, Some(&source(100))).unwrap(),
+            InsertOutcome::Skipped
+        );
+        assert_eq!(count_logs(&db), 2);
+    }
+}
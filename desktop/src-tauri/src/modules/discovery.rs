@@ -0,0 +1,201 @@
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use super::network::BindMode;
+
+/// mDNS (DNS-SD) で広告するサービスタイプ
+pub const SERVICE_TYPE: &str = "_vrcp._tcp.local.";
+/// モバイルアプリとのAPI互換性を表すバージョン (TXT の `version`)
+/// HTTP API に互換性のない変更を入れたら上げる
+pub const PROTOCOL_VERSION: &str = "1";
+
+/// LAN 内にこのPCのサーバーを広告する
+/// モバイルアプリはQRコードを読まなくても `_vrcp._tcp` を探せば接続先が分かる
+/// mDNS の応答は誰でも偽装できるので、証明書のフィンガープリントは載せない
+/// (ピン留めする値はQRコードからだけ受け取る)
+#[derive(Clone)]
+pub struct Advertiser {
+    daemon: ServiceDaemon,
+    /// サービスのインスタンス名 (PCのホスト名)
+    instance_name: String,
+    /// mDNS のホスト名 (xxx.local.)
+    host_name: String,
+}
+
+/// 登録中のサービス (停止時に登録解除する)
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertiser {
+    pub fn new() -> Result<Self, String> {
+        let daemon = ServiceDaemon::new().map_err(|e| e.to_string())?;
+        let host = gethostname::gethostname().to_string_lossy().to_string();
+        // mDNS のホスト名に使えない文字を置き換える
+        let label: String = host
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let label = if label.trim_matches('-').is_empty() {
+            "vrcp".to_string()
+        } else {
+            label
+        };
+        Ok(Advertiser {
+            daemon,
+            instance_name: if host.is_empty() {
+                "VRCP".to_string()
+            } else {
+                host
+            },
+            host_name: format!("{}.local.", label),
+        })
+    }
+
+    /// サービスを登録する
+    /// localhost のみで待ち受けている場合は LAN から接続できないので広告しない
+    pub fn advertise(
+        &self,
+        mode: &BindMode,
+        port: u16,
+        tls: bool,
+    ) -> Result<Option<Advertisement>, String> {
+        let address = match mode {
+            BindMode::Localhost => return Ok(None),
+            // 全インターフェースのアドレスを mdns-sd に自動で付けさせる
+            BindMode::Lan => String::new(),
+            BindMode::Interface(address) => address.clone(),
+        };
+
+        let proto = if tls { "https" } else { "http" };
+        let properties = [
+            ("version", PROTOCOL_VERSION.to_string()),
+            ("app", env!("CARGO_PKG_VERSION").to_string()),
+            ("proto", proto.to_string()),
+        ];
+
+        let mut info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &self.instance_name,
+            &self.host_name,
+            address.as_str(),
+            port,
+            &properties[..],
+        )
+        .map_err(|e| e.to_string())?;
+        if matches!(mode, BindMode::Lan) {
+            info = info.enable_addr_auto();
+        }
+
+        let fullname = info.get_fullname().to_string();
+        self.daemon.register(info).map_err(|e| e.to_string())?;
+        println!("Advertising {} on port {}", fullname, port);
+        Ok(Some(Advertisement {
+            daemon: self.daemon.clone(),
+            fullname,
+        }))
+    }
+}
+
+impl Advertisement {
+    /// 登録を解除する (goodbye パケットを送る)
+    pub fn withdraw(self) {
+        if let Err(e) = self.daemon.unregister(&self.fullname) {
+            eprintln!("Failed to unregister {}: {}", self.fullname, e);
+        }
+    }
+}
+
+/// mDNS で見つかったVRCPサーバー
+#[derive(Clone, Debug)]
+pub struct DiscoveredService {
+    /// サービスのインスタンス名 (広告しているPCのホスト名)
+    pub instance_name: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    /// TXT の `version` (API互換性のバージョン)
+    pub version: Option<String>,
+    /// TXT の `proto` (http / https)
+    pub proto: Option<String>,
+}
+
+/// `timeout` の間 `_vrcp._tcp` を探して、見つかったサーバーを返す (ブロッキング)
+/// TXT にはフィンガープリントを載せていないので、HTTPS の場合の検証はQRコードで行う
+pub fn browse(timeout: Duration) -> Result<Vec<DiscoveredService>, String> {
+    let daemon = ServiceDaemon::new().map_err(|e| e.to_string())?;
+    let receiver = daemon.browse(SERVICE_TYPE).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + timeout;
+    let mut found: HashMap<String, DiscoveredService> = HashMap::new();
+
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(remaining) {
+            Ok(ServiceEvent::ServiceResolved(service)) => {
+                let fullname = service.get_fullname().to_string();
+                let property = |key: &str| service.get_property_val_str(key).map(str::to_string);
+                let discovered = DiscoveredService {
+                    instance_name: fullname
+                        .strip_suffix(SERVICE_TYPE)
+                        .map(|name| name.trim_end_matches('.'))
+                        .unwrap_or(&fullname)
+                        .to_string(),
+                    addresses: service
+                        .get_addresses()
+                        .iter()
+                        .map(|ip| ip.to_ip_addr())
+                        .collect(),
+                    port: service.get_port(),
+                    version: property("version"),
+                    proto: property("proto"),
+                };
+                found.insert(fullname, discovered);
+            }
+            Ok(ServiceEvent::ServiceRemoved(_, fullname)) => {
+                found.remove(&fullname);
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    let _ = daemon.shutdown();
+    Ok(found.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advertised_service_is_found_by_browse() {
+        // 他のテストやPC上のVRCPと区別するため、使われていなさそうなポートで広告する
+        let port = 48_727;
+        let advertiser = Advertiser::new().unwrap();
+        let advertisement = advertiser
+            .advertise(&BindMode::Lan, port, true)
+            .unwrap()
+            .unwrap();
+
+        let found = browse(Duration::from_secs(5)).unwrap();
+        advertisement.withdraw();
+
+        let service = found
+            .iter()
+            .find(|service| service.port == port)
+            .expect("advertised service was not found");
+        assert_eq!(service.instance_name, advertiser.instance_name);
+        assert_eq!(service.version.as_deref(), Some(PROTOCOL_VERSION));
+        assert_eq!(service.proto.as_deref(), Some("https"));
+    }
+
+    #[test]
+    fn localhost_only_server_is_not_advertised() {
+        let advertiser = Advertiser::new().unwrap();
+        assert!(advertiser
+            .advertise(&BindMode::Localhost, 48_728, false)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,684 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{self, ClientConfig, DigitallySignedStruct, SignatureScheme};
+
+use super::db::LogDatabase;
+use super::tls;
+use super::watcher::{LogSource, Payload};
+
+/// 転送設定を保存する settings のキー (JSON)
+const FORWARD_CONFIG_KEY: &str = "forwarding";
+/// 1回の POST /ingest で送るイベント数
+const SEND_BATCH_SIZE: usize = 500;
+/// 送信失敗時の待ち時間 (失敗するたびに倍にする)
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// 送るものがない時に spool を見直す間隔
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// spool に貯める最大イベント数 (超えたら古いものから捨てる)
+const MAX_SPOOL_EVENTS: u32 = 200_000;
+
+/// 転送モード
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ForwardMode {
+    /// 転送しない
+    #[default]
+    Off,
+    /// このPCのDBに保存した上で転送する
+    Mirror,
+    /// このPCのDBには保存せず転送だけする (チェックポイントは保存する)
+    ForwardOnly,
+}
+
+/// 別のPCの VRCP へイベントを転送する設定
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct ForwardConfig {
+    pub mode: ForwardMode,
+    /// 転送先のURL (例: https://192.168.1.5:8727)
+    pub url: String,
+    /// 転送先のペアリングトークン
+    pub token: String,
+    /// 転送先の証明書の SHA-256 フィンガープリント (https の場合は必須)
+    pub fingerprint: Option<String>,
+}
+
+impl ForwardConfig {
+    /// 保存されている設定を読み込む (未設定・不正な値なら Off)
+    fn load(db: &LogDatabase) -> Self {
+        db.get_setting(FORWARD_CONFIG_KEY)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, db: &LogDatabase) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        db.set_setting(FORWARD_CONFIG_KEY, &json)
+            .map_err(|e| e.to_string())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.mode == ForwardMode::Off {
+            return Ok(());
+        }
+        let url = url::Url::parse(&self.url).map_err(|e| format!("Invalid URL: {}", e))?;
+        match url.scheme() {
+            "http" => Ok(()),
+            "https" if self.pinned_fingerprint().is_some() => Ok(()),
+            "https" => Err("The certificate fingerprint is required for https".to_string()),
+            scheme => Err(format!("Unsupported URL scheme: {}", scheme)),
+        }
+    }
+
+    /// 比較用に正規化したフィンガープリント (区切りと空白を除いた大文字の16進)
+    fn pinned_fingerprint(&self) -> Option<String> {
+        self.fingerprint
+            .as_deref()
+            .map(normalize_fingerprint)
+            .filter(|fp| !fp.is_empty())
+    }
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// 転送の状態
+#[derive(Clone, Debug, Default, Serialize, Type)]
+pub struct ForwardStatus {
+    /// 送信待ちのイベント数
+    pub pending_events: u32,
+    /// 最後に送信に成功した時刻 (RFC 3339)
+    pub last_success_at: Option<String>,
+    /// 最後に発生したエラー (成功すると消える)
+    pub last_error: Option<String>,
+    /// 転送先に拒否された、または spool の上限を超えて捨てたイベント数 (起動してからの合計)
+    pub discarded_events: u32,
+}
+
+/// spool に書き出すイベント (POST /ingest の1件と同じ形)
+#[derive(Serialize)]
+struct SpooledEvent<'a> {
+    #[serde(flatten)]
+    payload: &'a Payload,
+    source_file: &'a str,
+    source_offset: u64,
+}
+
+/// 送信待ちのイベントを貯めておく JSONL ファイル
+/// 送信済みの位置は別ファイルに保存し、全て送り終えたら両方を空にする
+struct Spool {
+    path: PathBuf,
+    offset_path: PathBuf,
+    /// 転送先に拒否されたイベントを書き出すファイル
+    rejected_path: PathBuf,
+    /// 送信待ちの行数
+    pending: Mutex<u32>,
+}
+
+impl Spool {
+    fn open(path: PathBuf) -> Self {
+        let offset_path = path.with_extension("offset");
+        let rejected_path = path.with_extension("rejected.jsonl");
+        let spool = Spool {
+            path,
+            offset_path,
+            rejected_path,
+            pending: Mutex::new(0),
+        };
+        // 前回送りきれなかった分を数える
+        let pending = spool
+            .read_lines(usize::MAX)
+            .map_or(0, |(lines, _)| lines.len() as u32);
+        *spool.pending.lock().unwrap() = pending;
+        spool
+    }
+
+    fn append(&self, lines: &[String]) -> io::Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        append_lines(&self.path, lines)?;
+        *pending += lines.len() as u32;
+        Ok(())
+    }
+
+    fn read_offset(&self) -> u64 {
+        fs::read_to_string(&self.offset_path)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// 送信済みの位置から最大 `max` 行読む (書きかけの行は含めない)
+    /// 戻り値の位置を `commit` に渡すと、そこまでを送信済みにする
+    fn read_lines(&self, max: usize) -> io::Result<(Vec<String>, u64)> {
+        let _pending = self.pending.lock().unwrap();
+        self.read_lines_locked(max)
+    }
+
+    /// `pending` のロックを持った状態で呼ぶ `read_lines`
+    fn read_lines_locked(&self, max: usize) -> io::Result<(Vec<String>, u64)> {
+        let mut offset = self.read_offset();
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+            Err(e) => return Err(e),
+        };
+        if offset > file.metadata()?.len() {
+            offset = 0;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut reader = BufReader::new(file);
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        while lines.len() < max {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            offset += read as u64;
+            lines.push(line.trim_end().to_string());
+        }
+        Ok((lines, offset))
+    }
+
+    fn commit(&self, offset: u64, count: usize) -> io::Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        *pending = pending.saturating_sub(count as u32);
+        let len = fs::metadata(&self.path).map_or(0, |m| m.len());
+        if offset >= len {
+            // 全て送信済み: ファイルを空にする
+            File::create(&self.path)?;
+            fs::write(&self.offset_path, "0")
+        } else {
+            fs::write(&self.offset_path, offset.to_string())
+        }
+    }
+
+    /// 転送先に拒否された行を別ファイルに移し、送信済みにする
+    fn reject(&self, lines: &[String], offset: u64) -> io::Result<()> {
+        append_lines(&self.rejected_path, lines)?;
+        self.commit(offset, lines.len())
+    }
+
+    /// 送信待ちが `max` 行を超えていたら古いものから捨てる (捨てた行数を返す)
+    fn trim(&self, max: u32) -> io::Result<u32> {
+        let mut pending = self.pending.lock().unwrap();
+        if *pending <= max {
+            return Ok(0);
+        }
+        let (dropped, offset) = self.read_lines_locked((*pending - max) as usize)?;
+        fs::write(&self.offset_path, offset.to_string())?;
+        let dropped = dropped.len() as u32;
+        *pending = pending.saturating_sub(dropped);
+        Ok(dropped)
+    }
+
+    fn pending(&self) -> u32 {
+        *self.pending.lock().unwrap()
+    }
+}
+
+fn append_lines(path: &Path, lines: &[String]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut buf = String::new();
+    for line in lines {
+        buf.push_str(line);
+        buf.push('\n');
+    }
+    file.write_all(buf.as_bytes())
+}
+
+/// 送信の失敗
+#[derive(Debug)]
+enum SendError {
+    /// 時間を置けば成功しうる (接続できない、認証エラー、サーバーエラーなど)
+    Retry(String),
+    /// 同じ内容を送り直しても成功しない (転送先が内容を受け付けなかった)
+    Rejected(String),
+}
+
+/// 送り直しても結果が変わらない応答か
+/// 401 (トークンの更新待ち)・408・429 以外の 4xx は、同じバッチを再送しても成功しない
+fn is_permanent(status: reqwest::StatusCode) -> bool {
+    use reqwest::StatusCode;
+    status.is_client_error()
+        && !matches!(
+            status,
+            StatusCode::UNAUTHORIZED | StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS
+        )
+}
+
+struct ForwarderInner {
+    db: LogDatabase,
+    config: RwLock<ForwardConfig>,
+    spool: Spool,
+    status: Mutex<ForwardStatus>,
+    /// 新しいイベントの追加や設定変更を送信タスクに知らせる
+    wake: Notify,
+}
+
+/// watcher が読んだイベントを別のPCの VRCP (POST /ingest) へ転送する
+/// 転送先に届かない間は spool ファイルに貯め、間隔を空けながら再送する
+#[derive(Clone)]
+pub struct Forwarder {
+    inner: Arc<ForwarderInner>,
+}
+
+impl Forwarder {
+    pub fn new(db: LogDatabase, spool_path: PathBuf) -> Self {
+        let config = ForwardConfig::load(&db);
+        Forwarder {
+            inner: Arc::new(ForwarderInner {
+                db,
+                config: RwLock::new(config),
+                spool: Spool::open(spool_path),
+                status: Mutex::new(ForwardStatus::default()),
+                wake: Notify::new(),
+            }),
+        }
+    }
+
+    pub fn mode(&self) -> ForwardMode {
+        self.inner.config.read().unwrap().mode
+    }
+
+    /// イベントを送信待ちに加える (転送が無効なら何もしない)
+    pub fn forward<'a>(&self, entries: impl IntoIterator<Item = (&'a Payload, &'a LogSource)>) {
+        if self.mode() == ForwardMode::Off {
+            return;
+        }
+        let lines: Vec<String> = entries
+            .into_iter()
+            .filter_map(|(payload, source)| {
+                serde_json::to_string(&SpooledEvent {
+                    payload,
+                    source_file: &source.file,
+                    source_offset: source.offset,
+                })
+                .ok()
+            })
+            .collect();
+        if lines.is_empty() {
+            return;
+        }
+        match self.inner.spool.append(&lines) {
+            Ok(()) => self.inner.wake.notify_one(),
+            Err(e) => self.set_error(format!("Failed to write forwarding spool: {}", e)),
+        }
+    }
+
+    fn set_error(&self, message: String) {
+        eprintln!("{}", message);
+        self.inner.status.lock().unwrap().last_error = Some(message);
+    }
+
+    /// 送らずに捨てたイベントを記録し、エラーとして表示する
+    fn discard(&self, count: u32, message: String) {
+        eprintln!("{}", message);
+        let mut status = self.inner.status.lock().unwrap();
+        status.discarded_events = status.discarded_events.saturating_add(count);
+        status.last_error = Some(message);
+    }
+
+    /// 送信タスクを開始する
+    pub fn spawn(&self) {
+        let forwarder = self.clone();
+        tauri::async_runtime::spawn(async move { forwarder.run().await });
+    }
+
+    async fn run(self) {
+        let mut client: Option<(ForwardConfig, reqwest::Client)> = None;
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            let config = self.inner.config.read().unwrap().clone();
+            if config.mode == ForwardMode::Off {
+                self.inner.wake.notified().await;
+                continue;
+            }
+
+            match self.inner.spool.trim(MAX_SPOOL_EVENTS) {
+                Ok(0) => {}
+                Ok(dropped) => self.discard(
+                    dropped,
+                    format!(
+                        "Forwarding spool exceeded {} events; dropped the oldest {}",
+                        MAX_SPOOL_EVENTS, dropped
+                    ),
+                ),
+                Err(e) => self.set_error(format!("Failed to trim forwarding spool: {}", e)),
+            }
+
+            let (lines, offset) = match self.inner.spool.read_lines(SEND_BATCH_SIZE) {
+                Ok(batch) => batch,
+                Err(e) => {
+                    self.set_error(format!("Failed to read forwarding spool: {}", e));
+                    self.wait(backoff).await;
+                    continue;
+                }
+            };
+            if lines.is_empty() {
+                self.wait(IDLE_INTERVAL).await;
+                continue;
+            }
+
+            // 設定が変わったらクライアントを作り直す
+            if client.as_ref().is_none_or(|(c, _)| *c != config) {
+                match build_client(&config) {
+                    Ok(built) => client = Some((config.clone(), built)),
+                    Err(e) => {
+                        self.set_error(e);
+                        self.wait(MAX_BACKOFF).await;
+                        continue;
+                    }
+                }
+            }
+            let Some((_, http)) = &client else {
+                continue;
+            };
+
+            match self.send(http, &config, &lines).await {
+                Ok(()) => {
+                    if let Err(e) = self.inner.spool.commit(offset, lines.len()) {
+                        self.set_error(format!("Failed to update forwarding spool: {}", e));
+                    }
+                    let mut status = self.inner.status.lock().unwrap();
+                    status.last_success_at = Some(chrono::Local::now().to_rfc3339());
+                    status.last_error = None;
+                    backoff = MIN_BACKOFF;
+                }
+                Err(SendError::Rejected(e)) => {
+                    // 再送しても受け付けられないので、別ファイルに移して先へ進む
+                    if let Err(e) = self.inner.spool.reject(&lines, offset) {
+                        self.set_error(format!("Failed to update forwarding spool: {}", e));
+                        self.wait(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                        continue;
+                    }
+                    self.discard(
+                        lines.len() as u32,
+                        format!(
+                            "Forwarding destination rejected {} events ({}); saved to {}",
+                            lines.len(),
+                            e,
+                            self.inner.spool.rejected_path.display()
+                        ),
+                    );
+                    backoff = MIN_BACKOFF;
+                }
+                Err(SendError::Retry(e)) => {
+                    self.set_error(format!("Failed to forward events: {}", e));
+                    self.wait(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    }
+
+    /// 指定時間か、新しいイベント・設定変更があるまで待つ
+    async fn wait(&self, duration: Duration) {
+        let _ = tokio::time::timeout(duration, self.inner.wake.notified()).await;
+    }
+
+    async fn send(
+        &self,
+        http: &reqwest::Client,
+        config: &ForwardConfig,
+        lines: &[String],
+    ) -> Result<(), SendError> {
+        // 壊れた行 (書き込み途中で終了した場合など) は飛ばす
+        let events: Vec<serde_json::Value> = lines
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let body = serde_json::json!({
+            "machine_id": self.inner.db.machine_id(),
+            "events": events,
+        });
+
+        let url = format!("{}/ingest", config.url.trim_end_matches('/'));
+        let response = http
+            .post(url)
+            .bearer_auth(&config.token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| SendError::Retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let detail = response.text().await.unwrap_or_default();
+        let message = format!("{} {}", status, detail).trim().to_string();
+        if is_permanent(status) {
+            Err(SendError::Rejected(message))
+        } else {
+            Err(SendError::Retry(message))
+        }
+    }
+}
+
+/// 転送先用の HTTP クライアント
+/// https の場合は CA ではなく、ペアリング時に得たフィンガープリントで証明書を検証する
+fn build_client(config: &ForwardConfig) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder().timeout(REQUEST_TIMEOUT);
+    if let Some(fingerprint) = config.pinned_fingerprint() {
+        let provider = Arc::new(crypto::ring::default_provider());
+        let tls = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                fingerprint,
+                provider,
+            }))
+            .with_no_client_auth();
+        builder = builder.use_preconfigured_tls(tls);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// 証明書のフィンガープリントが一致すれば信頼する (VRCP の自己署名証明書用)
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    fingerprint: String,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if normalize_fingerprint(&tls::fingerprint(end_entity)) == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "certificate fingerprint does not match".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+// commands
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_forward_config(forwarder: tauri::State<'_, Forwarder>) -> Result<ForwardConfig, String> {
+    Ok(forwarder.inner.config.read().unwrap().clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_forward_config(
+    forwarder: tauri::State<'_, Forwarder>,
+    config: ForwardConfig,
+) -> Result<(), String> {
+    config.validate()?;
+    config.save(&forwarder.inner.db)?;
+    *forwarder.inner.config.write().unwrap() = config;
+    forwarder.inner.status.lock().unwrap().last_error = None;
+    // 新しい設定ですぐに送り直す
+    forwarder.inner.wake.notify_one();
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_forward_status(forwarder: tauri::State<'_, Forwarder>) -> Result<ForwardStatus, String> {
+    let mut status = forwarder.inner.status.lock().unwrap().clone();
+    status.pending_events = forwarder.inner.spool.pending();
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn lines(range: std::ops::Range<u32>) -> Vec<String> {
+        range.map(|i| format!("{{\"n\":{}}}", i)).collect()
+    }
+
+    #[test]
+    fn spool_reads_and_commits_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = Spool::open(dir.path().join("spool.jsonl"));
+        spool.append(&lines(0..3)).unwrap();
+        spool.append(&lines(3..5)).unwrap();
+        assert_eq!(spool.pending(), 5);
+
+        let (batch, offset) = spool.read_lines(3).unwrap();
+        assert_eq!(batch, lines(0..3));
+        // commit するまでは同じ行を読み直す
+        assert_eq!(spool.read_lines(3).unwrap().0, lines(0..3));
+
+        spool.commit(offset, batch.len()).unwrap();
+        assert_eq!(spool.pending(), 2);
+        let (batch, offset) = spool.read_lines(3).unwrap();
+        assert_eq!(batch, lines(3..5));
+
+        // 全て送り終えたらファイルを空にする
+        spool.commit(offset, batch.len()).unwrap();
+        assert_eq!(spool.pending(), 0);
+        assert_eq!(fs::metadata(&spool.path).unwrap().len(), 0);
+        assert!(spool.read_lines(3).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn spool_skips_partial_line_and_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spool.jsonl");
+        let spool = Spool::open(path.clone());
+        spool.append(&lines(0..2)).unwrap();
+        let (batch, offset) = spool.read_lines(1).unwrap();
+        spool.commit(offset, batch.len()).unwrap();
+        // 書きかけの行
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"n\":")
+            .unwrap();
+
+        let reopened = Spool::open(path);
+        assert_eq!(reopened.pending(), 1);
+        assert_eq!(reopened.read_lines(10).unwrap().0, lines(1..2));
+    }
+
+    #[test]
+    fn spool_trim_drops_oldest_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = Spool::open(dir.path().join("spool.jsonl"));
+        spool.append(&lines(0..10)).unwrap();
+
+        assert_eq!(spool.trim(10).unwrap(), 0);
+        assert_eq!(spool.trim(4).unwrap(), 6);
+        assert_eq!(spool.pending(), 4);
+        assert_eq!(spool.read_lines(10).unwrap().0, lines(6..10));
+    }
+
+    #[test]
+    fn spool_reject_moves_batch_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = Spool::open(dir.path().join("spool.jsonl"));
+        spool.append(&lines(0..3)).unwrap();
+
+        let (batch, offset) = spool.read_lines(2).unwrap();
+        spool.reject(&batch, offset).unwrap();
+        assert_eq!(spool.pending(), 1);
+        assert_eq!(spool.read_lines(10).unwrap().0, lines(2..3));
+        let rejected = fs::read_to_string(&spool.rejected_path).unwrap();
+        assert_eq!(rejected.lines().collect::<Vec<_>>(), lines(0..2));
+    }
+
+    #[test]
+    fn client_errors_are_permanent_except_retryable_ones() {
+        assert!(is_permanent(StatusCode::BAD_REQUEST));
+        assert!(is_permanent(StatusCode::FORBIDDEN));
+        assert!(is_permanent(StatusCode::PAYLOAD_TOO_LARGE));
+        assert!(is_permanent(StatusCode::UNPROCESSABLE_ENTITY));
+
+        assert!(!is_permanent(StatusCode::UNAUTHORIZED));
+        assert!(!is_permanent(StatusCode::REQUEST_TIMEOUT));
+        assert!(!is_permanent(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_permanent(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_permanent(StatusCode::SERVICE_UNAVAILABLE));
+    }
+}
@@ -0,0 +1,35 @@
+use tokio::sync::broadcast;
+
+use crate::modules::db::LogRecord;
+
+/// 購読者が受信しきれずに溜められるイベント数
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// 新しく保存されたログを購読者 (WebSocket / SSE クライアントなど) へ配信するハブ
+#[derive(Clone)]
+pub struct EventHub {
+    sender: broadcast::Sender<LogRecord>,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        EventHub { sender }
+    }
+
+    /// 保存されたログを配信する (購読者がいなければ何もしない)
+    pub fn publish(&self, record: LogRecord) {
+        let _ = self.sender.send(record);
+    }
+
+    /// 以降に配信されるログを購読する
+    pub fn subscribe(&self) -> broadcast::Receiver<LogRecord> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
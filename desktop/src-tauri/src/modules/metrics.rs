@@ -0,0 +1,210 @@
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use super::db::{InsertOutcome, LogDatabase};
+use super::watcher::VrcLogEventKind;
+
+/// Prometheus 形式で公開する計測値 (アプリ全体で1つ)
+/// 無人で動かしている PC で記録が止まったことを検知するために使う
+pub struct Metrics {
+    lines_read: AtomicU64,
+    /// VrcLogEventKind::ALL と同じ順
+    events_matched: Vec<AtomicU64>,
+    events_inserted: AtomicU64,
+    events_skipped: AtomicU64,
+    insert_failures: AtomicU64,
+    rotations: AtomicU64,
+    /// (ルート, ステータスコード) ごとのリクエスト数
+    http_requests: Mutex<BTreeMap<(String, u16), u64>>,
+}
+
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| Metrics {
+        lines_read: AtomicU64::new(0),
+        events_matched: VrcLogEventKind::ALL
+            .iter()
+            .map(|_| AtomicU64::new(0))
+            .collect(),
+        events_inserted: AtomicU64::new(0),
+        events_skipped: AtomicU64::new(0),
+        insert_failures: AtomicU64::new(0),
+        rotations: AtomicU64::new(0),
+        http_requests: Mutex::new(BTreeMap::new()),
+    })
+}
+
+impl Metrics {
+    pub fn line_read(&self) {
+        self.lines_read.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn event_matched(&self, kind: VrcLogEventKind) {
+        if let Some(index) = VrcLogEventKind::ALL.iter().position(|k| *k == kind) {
+            self.events_matched[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn event_inserted(&self, outcome: InsertOutcome) {
+        let counter = match outcome {
+            InsertOutcome::Inserted(_) => &self.events_inserted,
+            InsertOutcome::Skipped => &self.events_skipped,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 保存できなかったイベント数を加算する
+    pub fn insert_failed(&self, events: u64) {
+        self.insert_failures.fetch_add(events, Ordering::Relaxed);
+    }
+
+    pub fn rotation_detected(&self) {
+        self.rotations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn http_request(&self, route: &str, status: u16) {
+        let mut requests = self.http_requests.lock().unwrap();
+        *requests.entry((route.to_string(), status)).or_insert(0) += 1;
+    }
+
+    /// Prometheus のテキスト形式に書き出す
+    /// DBサイズと最終イベントからの経過時間は書き出し時に DB から取得する
+    pub fn render(&self, db: &LogDatabase) -> String {
+        let mut out = String::new();
+
+        counter(
+            &mut out,
+            "vrcp_log_lines_read_total",
+            "Lines read from VRChat log files.",
+            [("", self.lines_read.load(Ordering::Relaxed))],
+        );
+        counter(
+            &mut out,
+            "vrcp_log_events_matched_total",
+            "Log lines recognized as an event, per event kind.",
+            VrcLogEventKind::ALL
+                .iter()
+                .zip(&self.events_matched)
+                .map(|(kind, count)| {
+                    (
+                        format!("kind=\"{}\"", kind.as_str()),
+                        count.load(Ordering::Relaxed),
+                    )
+                }),
+        );
+        counter(
+            &mut out,
+            "vrcp_db_inserts_total",
+            "Events passed to the database, by result.",
+            [
+                (
+                    "result=\"inserted\"",
+                    self.events_inserted.load(Ordering::Relaxed),
+                ),
+                (
+                    "result=\"skipped\"",
+                    self.events_skipped.load(Ordering::Relaxed),
+                ),
+            ],
+        );
+        counter(
+            &mut out,
+            "vrcp_db_insert_failures_total",
+            "Events that could not be saved to the database.",
+            [("", self.insert_failures.load(Ordering::Relaxed))],
+        );
+        counter(
+            &mut out,
+            "vrcp_log_rotations_total",
+            "Switches to a new VRChat log file.",
+            [("", self.rotations.load(Ordering::Relaxed))],
+        );
+        {
+            let requests = self.http_requests.lock().unwrap();
+            counter(
+                &mut out,
+                "vrcp_http_requests_total",
+                "HTTP requests handled, by route and status code.",
+                requests.iter().map(|((route, status), count)| {
+                    (
+                        format!("route=\"{}\",status=\"{}\"", escape(route), status),
+                        *count,
+                    )
+                }),
+            );
+        }
+
+        match db.size_bytes() {
+            Ok(size) => gauge(
+                &mut out,
+                "vrcp_db_size_bytes",
+                "Size of the database file.",
+                size as f64,
+            ),
+            Err(e) => eprintln!("Failed to get database size: {}", e),
+        }
+        match db.newest_event_time() {
+            Ok(Some(newest)) => gauge(
+                &mut out,
+                "vrcp_last_event_age_seconds",
+                "Seconds since the newest stored event occurred.",
+                (chrono::Local::now() - newest).num_milliseconds() as f64 / 1000.0,
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to get newest event: {}", e),
+        }
+
+        out
+    }
+}
+
+/// counter を1つ書き出す (ラベルが空ならラベルなし)
+fn counter<L: AsRef<str>>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (L, u64)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for (labels, value) in samples {
+        match labels.as_ref() {
+            "" => {
+                let _ = writeln!(out, "{} {}", name, value);
+            }
+            labels => {
+                let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+            }
+        }
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// ラベル値のエスケープ
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// HTTPリクエストをルート (パスのパターン) とステータスコードごとに数える middleware
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched".to_string(), |path| path.as_str().to_string());
+    let response = next.run(request).await;
+    metrics().http_request(&route, response.status().as_u16());
+    response
+}
@@ -0,0 +1,445 @@
+use rusqlite::{Connection, Transaction};
+use std::fmt;
+use std::path::Path;
+
+/// DBのスキーマ変更を1件分表す
+/// `version` は 1 から連番。適用済みのバージョンは SQLite の `user_version` に記録される
+struct Migration {
+    version: u32,
+    description: &'static str,
+    up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// マイグレーション一覧 (必ずバージョン順に追記すること。既存のものは変更しない)
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create logs and settings tables",
+        up: |tx| {
+            // user_version 導入前のDBにも既にテーブルがあるため IF NOT EXISTS で作る
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS logs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    timestamp TEXT NOT NULL,
+                    event_type TEXT NOT NULL,
+                    data TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS settings (
+                    key TEXT PRIMARY KEY,
+                    value TEXT NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        version: 2,
+        description: "create log_checkpoints table",
+        up: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS log_checkpoints (
+                    path TEXT PRIMARY KEY,
+                    file_id TEXT NOT NULL,
+                    offset INTEGER NOT NULL,
+                    updated_at TEXT NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        version: 3,
+        description: "add log source columns and natural key index",
+        up: |tx| {
+            // 重複排除用に取り込み元 (ファイル名と行の開始オフセット) を保持する
+            if !has_column(tx, "logs", "source_file")? {
+                tx.execute_batch(
+                    "ALTER TABLE logs ADD COLUMN source_file TEXT NOT NULL DEFAULT '';
+                     ALTER TABLE logs ADD COLUMN source_offset INTEGER NOT NULL DEFAULT -1;",
+                )?;
+            }
+            // 既存の重複行を削除してから UNIQUE 制約を張る
+            tx.execute_batch(
+                "DELETE FROM logs WHERE id NOT IN (
+                    SELECT MIN(id) FROM logs
+                    GROUP BY timestamp, event_type, data, source_file, source_offset
+                );
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_logs_natural_key
+                    ON logs (timestamp, event_type, data, source_file, source_offset);",
+            )
+        },
+    },
+    Migration {
+        version: 4,
+        description: "derive event_type from the JSON type tag and index it",
+        up: |tx| {
+            // 以前は Debug 出力から event_type を作っていたため、JSON の type タグで書き直す
+            tx.execute_batch(
+                "UPDATE logs SET event_type = json_extract(data, '$.type')
+                    WHERE json_extract(data, '$.type') IS NOT NULL
+                      AND event_type IS NOT json_extract(data, '$.type');
+                CREATE INDEX IF NOT EXISTS idx_logs_event_type_timestamp
+                    ON logs (event_type, timestamp);",
+            )
+        },
+    },
+    Migration {
+        version: 5,
+        description: "add machine_id column for events ingested from other PCs",
+        up: |tx| {
+            // このPCの識別子を発行し、既存の行は全てこのPCのものとする
+            tx.execute_batch(
+                "INSERT OR IGNORE INTO settings (key, value)
+                    VALUES ('machine_id', lower(hex(randomblob(16))));",
+            )?;
+            if !has_column(tx, "logs", "machine_id")? {
+                tx.execute_batch(
+                    "ALTER TABLE logs ADD COLUMN machine_id TEXT NOT NULL DEFAULT '';",
+                )?;
+            }
+            // 別のPCの同じ位置の行と衝突しないよう、重複判定に machine_id を含める
+            tx.execute_batch(
+                "UPDATE logs SET machine_id = (SELECT value FROM settings WHERE key = 'machine_id')
+                    WHERE machine_id = '';
+                DROP INDEX IF EXISTS idx_logs_natural_key;
+                CREATE UNIQUE INDEX idx_logs_natural_key
+                    ON logs (machine_id, timestamp, event_type, data, source_file, source_offset);",
+            )
+        },
+    },
+    Migration {
+        version: 6,
+        description: "create screenshots and screenshot_players tables",
+        up: |tx| {
+            // 写真を撮った時点のワールド・インスタンスと、その場にいたプレイヤー
+            // log_id は Screenshot イベントの logs.id
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS screenshots (
+                    log_id INTEGER PRIMARY KEY,
+                    timestamp TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    world_id TEXT,
+                    world_name TEXT,
+                    instance_id TEXT
+                );
+                CREATE INDEX IF NOT EXISTS idx_screenshots_timestamp
+                    ON screenshots (timestamp);
+                CREATE TABLE IF NOT EXISTS screenshot_players (
+                    log_id INTEGER NOT NULL,
+                    user_id TEXT NOT NULL,
+                    player_name TEXT NOT NULL,
+                    PRIMARY KEY (log_id, user_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_screenshot_players_user_id
+                    ON screenshot_players (user_id);",
+            )
+        },
+    },
+];
+
+/// このバージョンのVRCPが扱えるスキーマバージョン
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// DBが新しいバージョンのVRCPで作られていて開けない場合のエラー
+#[derive(Debug)]
+pub struct SchemaTooNewError {
+    /// DBに記録されているスキーマバージョン
+    pub found: u32,
+    /// このバージョンが扱える最大のスキーマバージョン
+    pub supported: u32,
+}
+
+impl fmt::Display for SchemaTooNewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The database was created by a newer version of VRCP (schema v{}, this version supports up to v{}). Please update VRCP.",
+            self.found, self.supported
+        )
+    }
+}
+
+impl std::error::Error for SchemaTooNewError {}
+
+/// 未適用のマイグレーションを順番に適用する
+/// 既存のDBを変更する前に `backup_dir` へバックアップを作成する
+pub fn run_migrations(
+    conn: &mut Connection,
+    backup_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let current: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(Box::new(SchemaTooNewError {
+            found: current,
+            supported: latest,
+        }));
+    }
+    if current == latest {
+        return Ok(());
+    }
+
+    // 既存データがある場合のみバックアップ (新規作成時は不要)
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables {
+        let backup_path = backup_dir.join(format!(
+            "vrcp.v{}.{}.bak.db",
+            current,
+            chrono::Local::now().format("%Y%m%d%H%M%S")
+        ));
+        println!("Backing up database to {:?} before migration", backup_path);
+        conn.execute(
+            "VACUUM INTO ?1",
+            [backup_path.to_string_lossy().to_string()],
+        )?;
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        println!(
+            "Applying migration v{}: {}",
+            migration.version, migration.description
+        );
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        // user_version の更新も同じトランザクションで行う
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// テーブルに指定カラムが存在するか
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// user_version 導入前の DB (v1 相当のテーブルのみ)
+    fn legacy_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE logs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                event_type TEXT NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+            INSERT INTO settings (key, value) VALUES ('server_port', '8727');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn insert_legacy(conn: &Connection, timestamp: &str, event_type: &str, data: &str) {
+        conn.execute(
+            "INSERT INTO logs (timestamp, event_type, data) VALUES (?1, ?2, ?3)",
+            [timestamp, event_type, data],
+        )
+        .unwrap();
+    }
+
+    fn user_version(conn: &Connection) -> u32 {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    const JOIN_ALICE: &str =
+        r#"{"type":"PlayerJoin","data":{"player_name":"Alice","user_id":"usr_a"}}"#;
+    const JOIN_BOB: &str =
+        r#"{"type":"PlayerJoin","data":{"player_name":"Bob","user_id":"usr_b"}}"#;
+
+    #[test]
+    fn fresh_db_reaches_latest_version_without_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn, dir.path()).unwrap();
+
+        assert_eq!(user_version(&conn), latest_version());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn legacy_db_is_migrated_with_data_and_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = legacy_db();
+        insert_legacy(&conn, "2024-01-01 10:00:00", "PlayerJoin", JOIN_ALICE);
+        insert_legacy(&conn, "2024-01-01 10:00:01", "PlayerJoin", JOIN_BOB);
+
+        run_migrations(&mut conn, dir.path()).unwrap();
+
+        assert_eq!(user_version(&conn), latest_version());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM logs"), 2);
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM settings WHERE key = 'server_port' AND value = '8727'"
+            ),
+            1
+        );
+
+        // 変更前の状態がバックアップされている
+        let backups: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let name = backups[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert!(
+            name.starts_with("vrcp.v0.") && name.ends_with(".bak.db"),
+            "{}",
+            name
+        );
+        let backup = Connection::open(&backups[0]).unwrap();
+        assert_eq!(user_version(&backup), 0);
+        assert_eq!(count(&backup, "SELECT COUNT(*) FROM logs"), 2);
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        let err = run_migrations(&mut conn, dir.path()).unwrap_err();
+        let err = err.downcast_ref::<SchemaTooNewError>().unwrap();
+        assert_eq!(err.found, latest_version() + 1);
+        assert_eq!(err.supported, latest_version());
+        assert_eq!(user_version(&conn), latest_version() + 1);
+    }
+
+    #[test]
+    fn v3_keeps_one_row_per_natural_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = legacy_db();
+        for _ in 0..3 {
+            insert_legacy(&conn, "2024-01-01 10:00:00", "PlayerJoin", JOIN_ALICE);
+        }
+        insert_legacy(&conn, "2024-01-01 10:00:01", "PlayerJoin", JOIN_ALICE);
+        insert_legacy(&conn, "2024-01-01 10:00:00", "PlayerJoin", JOIN_BOB);
+
+        run_migrations(&mut conn, dir.path()).unwrap();
+
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM logs"), 3);
+        // 最初の行が残る
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT MIN(id) FROM logs WHERE timestamp = '2024-01-01 10:00:00' AND data LIKE '%Alice%'"
+            ),
+            1
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM logs WHERE source_file = '' AND source_offset = -1"
+            ),
+            3
+        );
+    }
+
+    #[test]
+    fn v4_rewrites_event_type_from_json_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = legacy_db();
+        // 以前は Debug 出力から作っていた
+        insert_legacy(
+            &conn,
+            "2024-01-01 10:00:00",
+            r#"PlayerJoin { player_name: "Alice", user_id: "usr_a" }"#,
+            JOIN_ALICE,
+        );
+        insert_legacy(
+            &conn,
+            "2024-01-01 10:00:01",
+            "AppStart",
+            r#"{"type":"AppStart"}"#,
+        );
+
+        run_migrations(&mut conn, dir.path()).unwrap();
+
+        let types: Vec<String> = conn
+            .prepare("SELECT event_type FROM logs ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(types, vec!["PlayerJoin", "AppStart"]);
+    }
+
+    #[test]
+    fn v5_backfills_machine_id_and_rebuilds_natural_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = legacy_db();
+        insert_legacy(&conn, "2024-01-01 10:00:00", "PlayerJoin", JOIN_ALICE);
+        insert_legacy(&conn, "2024-01-01 10:00:01", "PlayerJoin", JOIN_BOB);
+
+        run_migrations(&mut conn, dir.path()).unwrap();
+
+        let machine_id: String = conn
+            .query_row(
+                "SELECT value FROM settings WHERE key = 'machine_id'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(machine_id.len(), 32);
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM logs WHERE machine_id = (SELECT value FROM settings WHERE key = 'machine_id')"),
+            2
+        );
+
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_index_info('idx_logs_natural_key') ORDER BY seqno")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                "machine_id",
+                "timestamp",
+                "event_type",
+                "data",
+                "source_file",
+                "source_offset"
+            ]
+        );
+
+        // 同じイベントでも別のPCのものは保存でき、同じPCのものは無視される
+        let insert = "INSERT OR IGNORE INTO logs (timestamp, event_type, data, source_file, source_offset, machine_id)
+                      VALUES ('2024-01-01 10:00:00', 'PlayerJoin', ?1, '', -1, ?2)";
+        assert_eq!(conn.execute(insert, [JOIN_ALICE, "other"]).unwrap(), 1);
+        assert_eq!(
+            conn.execute(insert, [JOIN_ALICE, machine_id.as_str()])
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -1,6 +1,16 @@
 // desktop/src-tauri/src/modules/mod.rs
 
+pub mod auth;
+pub mod backfill;
 pub mod db;
+pub mod discovery;
+pub mod forwarder;
+pub mod hub;
+pub mod metrics;
+pub mod migrations;
+pub mod network;
+pub mod screenshot;
 pub mod server;
 pub mod systray;
+pub mod tls;
 pub mod watcher;
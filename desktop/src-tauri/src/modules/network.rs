@@ -0,0 +1,175 @@
+use local_ip_address::{list_afinet_netifas, local_ip};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Socket, Type as SocketType};
+use specta::Type;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::TcpListener;
+
+use super::db::LogDatabase;
+
+/// 待ち受けモードを保存する settings のキー (JSON)
+const BIND_MODE_KEY: &str = "bind_mode";
+/// Lan モードで接続用URLに使うアドレスを保存する settings のキー
+const ADVERTISED_ADDRESS_KEY: &str = "advertised_address";
+
+/// HTTPサーバーの待ち受け範囲
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(tag = "mode", content = "address")]
+pub enum BindMode {
+    /// このPCからのみ (127.0.0.1 / ::1)
+    Localhost,
+    /// 全てのネットワーク (0.0.0.0 / ::)
+    #[default]
+    Lan,
+    /// 指定したアドレスのインターフェースのみ
+    Interface(String),
+}
+
+/// 待ち受けるアドレス
+pub struct BindTarget {
+    pub addr: SocketAddr,
+    /// false の場合は bind に失敗しても続行する (IPv6 が無効な環境向け)
+    pub required: bool,
+}
+
+impl BindMode {
+    /// 保存されている設定を読み込む (未設定・不正な値なら Lan)
+    pub fn load(db: &LogDatabase) -> Self {
+        db.get_setting(BIND_MODE_KEY)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, db: &LogDatabase) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        db.set_setting(BIND_MODE_KEY, &json)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 待ち受けるアドレスの一覧
+    pub fn bind_targets(&self, port: u16) -> Result<Vec<BindTarget>, String> {
+        let targets = match self {
+            BindMode::Localhost => vec![
+                (IpAddr::V4(Ipv4Addr::LOCALHOST), true),
+                (IpAddr::V6(Ipv6Addr::LOCALHOST), false),
+            ],
+            BindMode::Lan => vec![
+                (IpAddr::V4(Ipv4Addr::UNSPECIFIED), true),
+                (IpAddr::V6(Ipv6Addr::UNSPECIFIED), false),
+            ],
+            BindMode::Interface(address) => vec![(parse_ip(address)?, true)],
+        };
+        Ok(targets
+            .into_iter()
+            .map(|(ip, required)| BindTarget {
+                addr: SocketAddr::new(ip, port),
+                required,
+            })
+            .collect())
+    }
+
+    /// 接続用URLに使うアドレス
+    /// Lan モードでは選択されたアドレスを使い、未選択かインターフェースから外れていれば既定経路のアドレスにする
+    pub fn advertised_ip(&self, db: &LogDatabase) -> Result<IpAddr, String> {
+        match self {
+            BindMode::Localhost => Ok(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            BindMode::Lan => match load_advertised_address(db) {
+                Some(ip) if is_assigned(ip)? => Ok(ip),
+                _ => local_ip().map_err(|e| e.to_string()),
+            },
+            BindMode::Interface(address) => parse_ip(address),
+        }
+    }
+}
+
+/// Lan モードで接続用URLに使うアドレス (未選択なら None)
+fn load_advertised_address(db: &LogDatabase) -> Option<IpAddr> {
+    db.get_setting(ADVERTISED_ADDRESS_KEY)
+        .ok()
+        .and_then(|address| address.parse().ok())
+}
+
+/// このPCのいずれかのインターフェースに割り当てられているか
+fn is_assigned(ip: IpAddr) -> Result<bool, String> {
+    let interfaces = list_afinet_netifas().map_err(|e| e.to_string())?;
+    Ok(interfaces.iter().any(|(_, address)| *address == ip))
+}
+
+fn parse_ip(address: &str) -> Result<IpAddr, String> {
+    address
+        .parse()
+        .map_err(|_| format!("Invalid IP address: {}", address))
+}
+
+/// アドレスを bind して listener を作る
+/// IPv6 は IPv4 側と同じポートを共存させるため IPV6_V6ONLY を有効にする
+pub fn bind_listener(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), SocketType::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// ネットワークインターフェースのアドレス
+#[derive(Clone, Serialize, Type)]
+pub struct NetworkAddress {
+    /// インターフェース名
+    pub interface: String,
+    pub address: String,
+    pub is_ipv6: bool,
+    pub is_loopback: bool,
+}
+
+// commands
+
+/// PCの全インターフェースのアドレスを取得する
+#[tauri::command]
+#[specta::specta]
+pub fn list_network_addresses() -> Result<Vec<NetworkAddress>, String> {
+    let interfaces = list_afinet_netifas().map_err(|e| e.to_string())?;
+    Ok(interfaces
+        .into_iter()
+        .map(|(interface, ip)| NetworkAddress {
+            interface,
+            address: ip.to_string(),
+            is_ipv6: ip.is_ipv6(),
+            is_loopback: ip.is_loopback(),
+        })
+        .collect())
+}
+
+/// Lan モードで接続用URLに使うアドレスを取得する (自動なら null)
+#[tauri::command]
+#[specta::specta]
+pub fn get_advertised_address(db: tauri::State<'_, LogDatabase>) -> Result<Option<String>, String> {
+    Ok(load_advertised_address(&db).map(|ip| ip.to_string()))
+}
+
+/// Lan モードで接続用URLに使うアドレスを設定する (null なら自動)
+#[tauri::command]
+#[specta::specta]
+pub fn set_advertised_address(
+    db: tauri::State<'_, LogDatabase>,
+    address: Option<String>,
+) -> Result<(), String> {
+    let value = match address {
+        Some(address) => {
+            let ip = parse_ip(&address)?;
+            if !is_assigned(ip)? {
+                return Err(format!("{} is not assigned to this PC", address));
+            }
+            ip.to_string()
+        }
+        None => String::new(),
+    };
+    db.set_setting(ADVERTISED_ADDRESS_KEY, &value)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,512 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::db::{LogDatabase, ScreenshotInfo, ScreenshotPlayer};
+
+/// PNG ファイルの先頭8バイト
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+/// 埋め込むテキストチャンクのキーワード
+const KEY_TIMESTAMP: &str = "VRCP:Timestamp";
+const KEY_WORLD_ID: &str = "VRCP:WorldId";
+const KEY_WORLD_NAME: &str = "VRCP:WorldName";
+const KEY_INSTANCE_ID: &str = "VRCP:InstanceId";
+/// プレイヤー一覧 (ScreenshotPlayer の JSON 配列)
+const KEY_PLAYERS: &str = "VRCP:Players";
+/// PNG のチャンク長の上限 (仕様上 2^31-1)
+const MAX_CHUNK_LEN: u32 = i32::MAX as u32;
+/// 読み取るテキストチャンクの上限 (これより大きいものは VRCP のものではないので読み飛ばす)
+const MAX_TEXT_CHUNK_LEN: u32 = 1024 * 1024;
+/// 写真にメタデータを埋め込むかの設定キー (既定は無効)
+const EMBED_METADATA_KEY: &str = "embed_screenshot_metadata";
+/// VRChat がファイルを書き終えるのを待つ間隔と回数
+const WRITE_RETRY_DELAY: Duration = Duration::from_secs(1);
+const WRITE_RETRIES: u32 = 5;
+
+/// PNG のチャンク1つ分
+struct Chunk {
+    kind: [u8; 4],
+    data: Vec<u8>,
+}
+
+impl Chunk {
+    /// 圧縮しない iTXt チャンクを作る (言語タグ・翻訳キーワードは空)
+    fn itxt(keyword: &str, text: &str) -> Self {
+        let mut data = Vec::with_capacity(keyword.len() + text.len() + 5);
+        data.extend_from_slice(keyword.as_bytes());
+        // NUL, 圧縮フラグ, 圧縮方式, 言語タグ NUL, 翻訳キーワード NUL
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data.extend_from_slice(text.as_bytes());
+        Chunk {
+            kind: *b"iTXt",
+            data,
+        }
+    }
+
+    fn crc(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&self.kind);
+        hasher.update(&self.data);
+        hasher.finalize()
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// PNG を IEND までチャンクに分割する
+/// IEND がなければ書き込み途中とみなしてエラーにする
+fn parse_chunks(bytes: &[u8]) -> io::Result<Vec<Chunk>> {
+    let mut rest = bytes
+        .strip_prefix(PNG_SIGNATURE)
+        .ok_or_else(|| invalid("not a PNG file"))?;
+    let mut chunks = Vec::new();
+    loop {
+        if rest.len() < 12 {
+            return Err(invalid("truncated PNG file"));
+        }
+        let len = u32::from_be_bytes(rest[0..4].try_into().unwrap());
+        if len > MAX_CHUNK_LEN {
+            return Err(invalid("PNG chunk too long"));
+        }
+        let len = len as usize;
+        if rest.len() < 12 + len {
+            return Err(invalid("truncated PNG file"));
+        }
+        let chunk = Chunk {
+            kind: rest[4..8].try_into().unwrap(),
+            data: rest[8..8 + len].to_vec(),
+        };
+        let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+        if crc != chunk.crc() {
+            return Err(invalid("PNG chunk CRC mismatch"));
+        }
+        rest = &rest[12 + len..];
+        let is_end = &chunk.kind == b"IEND";
+        chunks.push(chunk);
+        if is_end {
+            return Ok(chunks);
+        }
+    }
+}
+
+fn encode_chunks(chunks: &[Chunk]) -> Vec<u8> {
+    let size = chunks.iter().map(|c| c.data.len() + 12).sum::<usize>();
+    let mut out = Vec::with_capacity(PNG_SIGNATURE.len() + size);
+    out.extend_from_slice(PNG_SIGNATURE);
+    for chunk in chunks {
+        out.extend_from_slice(&(chunk.data.len() as u32).to_be_bytes());
+        out.extend_from_slice(&chunk.kind);
+        out.extend_from_slice(&chunk.data);
+        out.extend_from_slice(&chunk.crc().to_be_bytes());
+    }
+    out
+}
+
+/// tEXt / iTXt チャンクの (キーワード, テキスト) を取り出す (圧縮された iTXt は対象外)
+fn read_text(kind: &[u8; 4], data: &[u8]) -> Option<(String, String)> {
+    let nul = data.iter().position(|b| *b == 0)?;
+    // キーワードは Latin-1
+    let keyword: String = data[..nul].iter().map(|b| *b as char).collect();
+    let rest = &data[nul + 1..];
+    match kind {
+        b"tEXt" => Some((keyword, rest.iter().map(|b| *b as char).collect())),
+        b"iTXt" => {
+            let (&[compressed, _method], rest) = rest.split_first_chunk::<2>()?;
+            if compressed != 0 {
+                return None;
+            }
+            // 言語タグと翻訳キーワードを読み飛ばす
+            let lang_end = rest.iter().position(|b| *b == 0)?;
+            let rest = &rest[lang_end + 1..];
+            let translated_end = rest.iter().position(|b| *b == 0)?;
+            let text = String::from_utf8(rest[translated_end + 1..].to_vec()).ok()?;
+            Some((keyword, text))
+        }
+        _ => None,
+    }
+}
+
+/// 写真の撮影時の状況を PNG のテキストチャンクとして書き込む
+/// 画像データはそのままで、以前に書き込んだ VRCP のチャンクは置き換える
+pub fn write_metadata(path: &Path, info: &ScreenshotInfo) -> io::Result<()> {
+    let bytes = fs::read(path)?;
+    let mut chunks: Vec<Chunk> = parse_chunks(&bytes)?
+        .into_iter()
+        .filter(|chunk| {
+            read_text(&chunk.kind, &chunk.data)
+                .is_none_or(|(keyword, _)| !keyword.starts_with("VRCP:"))
+        })
+        .collect();
+
+    let mut texts = vec![(KEY_TIMESTAMP, info.timestamp.clone())];
+    let optional = [
+        (KEY_WORLD_ID, &info.world_id),
+        (KEY_WORLD_NAME, &info.world_name),
+        (KEY_INSTANCE_ID, &info.instance_id),
+    ];
+    for (keyword, value) in optional {
+        if let Some(value) = value {
+            texts.push((keyword, value.clone()));
+        }
+    }
+    texts.push((KEY_PLAYERS, serde_json::to_string(&info.players)?));
+
+    // IEND の直前に挿入する
+    let iend = chunks.len() - 1;
+    chunks.splice(
+        iend..iend,
+        texts
+            .iter()
+            .map(|(keyword, text)| Chunk::itxt(keyword, text)),
+    );
+
+    // 書き込み途中で失敗しても元の写真が壊れないよう、別ファイルに書いてから置き換える
+    let tmp_path = path.with_extension("png.vrcp-tmp");
+    fs::write(&tmp_path, encode_chunks(&chunks))?;
+    fs::rename(&tmp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp_path);
+    })
+}
+
+/// PNG に埋め込まれた撮影時の状況を読み取る (VRCP のチャンクがなければ None)
+/// 画像データは読み飛ばすので、大きなファイルでも先頭から順にチャンクの見出しを読むだけで済む
+/// チャンク長はファイルの残りの大きさで検証し、読み取ったテキストチャンクは CRC も検証する
+pub fn read_metadata(path: &Path) -> io::Result<Option<ScreenshotInfo>> {
+    let file = File::open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut signature = [0u8; 8];
+    reader.read_exact(&mut signature)?;
+    if &signature != PNG_SIGNATURE {
+        return Err(invalid("not a PNG file"));
+    }
+    remaining -= 8;
+
+    let mut texts = HashMap::new();
+    loop {
+        // IEND の前にファイルが終わっていれば書き込み途中
+        if remaining < 12 {
+            return Err(invalid("truncated PNG file"));
+        }
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let len = u32::from_be_bytes(header[0..4].try_into().unwrap());
+        let kind: [u8; 4] = header[4..8].try_into().unwrap();
+        if len > MAX_CHUNK_LEN {
+            return Err(invalid("PNG chunk too long"));
+        }
+        if u64::from(len) > remaining - 12 {
+            return Err(invalid("truncated PNG file"));
+        }
+        remaining -= u64::from(len) + 12;
+        if &kind == b"IEND" {
+            break;
+        }
+        if (&kind == b"tEXt" || &kind == b"iTXt") && len <= MAX_TEXT_CHUNK_LEN {
+            let mut chunk = Chunk {
+                kind,
+                data: vec![0u8; len as usize],
+            };
+            reader.read_exact(&mut chunk.data)?;
+            let mut crc = [0u8; 4];
+            reader.read_exact(&mut crc)?;
+            if u32::from_be_bytes(crc) != chunk.crc() {
+                return Err(invalid("PNG chunk CRC mismatch"));
+            }
+            if let Some((keyword, text)) = read_text(&chunk.kind, &chunk.data) {
+                if keyword.starts_with("VRCP:") {
+                    texts.insert(keyword, text);
+                }
+            }
+        } else {
+            // データと CRC を読み飛ばす
+            reader.seek_relative(len as i64 + 4)?;
+        }
+    }
+
+    let Some(timestamp) = texts.remove(KEY_TIMESTAMP) else {
+        return Ok(None);
+    };
+    let players: Vec<ScreenshotPlayer> = match texts.remove(KEY_PLAYERS) {
+        Some(json) => serde_json::from_str(&json)?,
+        None => Vec::new(),
+    };
+    Ok(Some(ScreenshotInfo {
+        timestamp,
+        path: path.to_string_lossy().to_string(),
+        world_id: texts.remove(KEY_WORLD_ID),
+        world_name: texts.remove(KEY_WORLD_NAME),
+        instance_id: texts.remove(KEY_INSTANCE_ID),
+        players,
+    }))
+}
+
+/// 写真にメタデータを埋め込む設定か
+fn embed_enabled(db: &LogDatabase) -> bool {
+    db.get_setting(EMBED_METADATA_KEY)
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+/// 保存した Screenshot イベント (logs.id) の写真に、撮影時の状況をバックグラウンドで書き込む
+/// 設定で有効になっていなければ何もしない
+pub fn embed_in_background(db: &LogDatabase, log_id: i64) {
+    if !embed_enabled(db) {
+        return;
+    }
+    let db = db.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let info = match db.get_screenshot(log_id) {
+            Ok(Some(info)) => info,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("Failed to load screenshot {}: {}", log_id, e);
+                return;
+            }
+        };
+        // ログに出力された直後はまだ書き込み中のことがあるので、何度か試す
+        for attempt in 1..=WRITE_RETRIES {
+            std::thread::sleep(WRITE_RETRY_DELAY);
+            match write_metadata(Path::new(&info.path), &info) {
+                Ok(()) => return,
+                Err(e) if attempt == WRITE_RETRIES => {
+                    eprintln!("Failed to embed metadata into {}: {}", info.path, e);
+                }
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+/// 過去ログから取り込んだ Screenshot イベント (logs.id) の写真に、撮影時の状況を書き込む
+/// 書き込み済みの写真なので待たずに書き込み、移動・削除された写真は無視する
+pub fn embed_existing(db: &LogDatabase, log_id: i64) {
+    if !embed_enabled(db) {
+        return;
+    }
+    let info = match db.get_screenshot(log_id) {
+        Ok(Some(info)) => info,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Failed to load screenshot {}: {}", log_id, e);
+            return;
+        }
+    };
+    match write_metadata(Path::new(&info.path), &info) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("Failed to embed metadata into {}: {}", info.path, e),
+    }
+}
+
+/// ディレクトリ以下の PNG ファイルを全て列挙する
+/// シンボリックリンクはたどらない (ループや選択したフォルダの外を読まないように)
+fn find_png_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            find_png_files(&path, files)?;
+        } else if file_type.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// ディレクトリ以下の写真から、埋め込まれた撮影時の状況を DB に取り込む (取り込んだ枚数を返す)
+fn import_dir(db: &LogDatabase, dir: &Path) -> Result<usize, String> {
+    let mut files = Vec::new();
+    find_png_files(dir, &mut files).map_err(|e| e.to_string())?;
+
+    let mut imported = 0;
+    for file in files {
+        match read_metadata(&file) {
+            Ok(Some(info)) => {
+                if db.import_screenshot(&info).map_err(|e| e.to_string())? {
+                    imported += 1;
+                }
+            }
+            Ok(None) => {}
+            // 壊れたファイルがあっても他の写真は取り込む
+            Err(e) => eprintln!("Failed to read {:?}: {}", file, e),
+        }
+    }
+    Ok(imported)
+}
+
+// commands
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_embed_screenshot_metadata(db: tauri::State<'_, LogDatabase>) -> Result<bool, String> {
+    Ok(embed_enabled(&db))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_embed_screenshot_metadata(
+    db: tauri::State<'_, LogDatabase>,
+    enabled: bool,
+) -> Result<(), String> {
+    db.set_setting(EMBED_METADATA_KEY, &enabled.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn import_screenshots(
+    db: tauri::State<'_, LogDatabase>,
+    dir: String,
+) -> Result<usize, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || import_dir(&db, Path::new(&dir)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 画像データと XMP の iTXt を含む PNG
+    fn sample_png() -> Vec<u8> {
+        encode_chunks(&[
+            Chunk {
+                kind: *b"IHDR",
+                data: vec![0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0],
+            },
+            Chunk::itxt("XML:com.adobe.xmp", "<x:xmpmeta>VRChat</x:xmpmeta>"),
+            Chunk {
+                kind: *b"IDAT",
+                data: (0..=255).collect(),
+            },
+            Chunk {
+                kind: *b"IEND",
+                data: Vec::new(),
+            },
+        ])
+    }
+
+    fn sample_info(path: &Path, world_name: &str) -> ScreenshotInfo {
+        ScreenshotInfo {
+            timestamp: "2024-01-01 10:00:00".to_string(),
+            path: path.to_string_lossy().to_string(),
+            world_id: Some("wrld_a".to_string()),
+            world_name: Some(world_name.to_string()),
+            instance_id: Some("1~private".to_string()),
+            players: vec![ScreenshotPlayer {
+                user_id: "usr_a".to_string(),
+                player_name: "Alice".to_string(),
+            }],
+        }
+    }
+
+    fn setup() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("VRChat_2024-01-01_10-00-00.000_1920x1080.png");
+        fs::write(&path, sample_png()).unwrap();
+        (dir, path)
+    }
+
+    /// VRCP 以外のチャンクを元の順に並べ直したファイル
+    fn without_vrcp_chunks(bytes: &[u8]) -> Vec<u8> {
+        let chunks: Vec<Chunk> = parse_chunks(bytes)
+            .unwrap()
+            .into_iter()
+            .filter(|chunk| {
+                read_text(&chunk.kind, &chunk.data)
+                    .is_none_or(|(keyword, _)| !keyword.starts_with("VRCP:"))
+            })
+            .collect();
+        encode_chunks(&chunks)
+    }
+
+    #[test]
+    fn written_metadata_is_read_back() {
+        let (_dir, path) = setup();
+        let info = sample_info(&path, "Home");
+        write_metadata(&path, &info).unwrap();
+        assert_eq!(read_metadata(&path).unwrap(), Some(info));
+    }
+
+    #[test]
+    fn other_chunks_are_kept_byte_for_byte() {
+        let (_dir, path) = setup();
+        write_metadata(&path, &sample_info(&path, "Home")).unwrap();
+        let written = fs::read(&path).unwrap();
+        assert_ne!(written, sample_png());
+        assert_eq!(without_vrcp_chunks(&written), sample_png());
+    }
+
+    #[test]
+    fn writing_again_replaces_metadata() {
+        let (_dir, path) = setup();
+        write_metadata(&path, &sample_info(&path, "Home")).unwrap();
+        let once = fs::read(&path).unwrap().len();
+
+        let info = sample_info(&path, "Park");
+        write_metadata(&path, &info).unwrap();
+        // ワールド名の長さは同じなので、チャンクが増えていなければサイズも同じ
+        assert_eq!(fs::read(&path).unwrap().len(), once);
+        assert_eq!(read_metadata(&path).unwrap(), Some(info));
+    }
+
+    #[test]
+    fn png_without_iend_is_rejected() {
+        let (_dir, path) = setup();
+        let png = sample_png();
+        // IEND チャンク (12 バイト) がまだ書かれていない
+        let truncated = &png[..png.len() - 12];
+        fs::write(&path, truncated).unwrap();
+
+        assert!(write_metadata(&path, &sample_info(&path, "Home")).is_err());
+        assert_eq!(fs::read(&path).unwrap(), truncated);
+    }
+
+    #[test]
+    fn metadata_of_png_without_iend_is_rejected() {
+        let (_dir, path) = setup();
+        write_metadata(&path, &sample_info(&path, "Home")).unwrap();
+        let png = fs::read(&path).unwrap();
+        fs::write(&path, &png[..png.len() - 12]).unwrap();
+        assert!(read_metadata(&path).is_err());
+    }
+
+    #[test]
+    fn chunk_longer_than_file_is_rejected() {
+        let (_dir, path) = setup();
+        let mut png = sample_png();
+        // IHDR の長さを 2^31-1 に書き換える
+        png[8..12].copy_from_slice(&(i32::MAX as u32).to_be_bytes());
+        fs::write(&path, &png).unwrap();
+        assert!(read_metadata(&path).is_err());
+
+        png[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        fs::write(&path, &png).unwrap();
+        assert!(read_metadata(&path).is_err());
+    }
+
+    #[test]
+    fn corrupted_text_chunk_is_rejected() {
+        let (_dir, path) = setup();
+        write_metadata(&path, &sample_info(&path, "Home")).unwrap();
+        let mut png = fs::read(&path).unwrap();
+        // 埋め込んだワールド名の1文字を書き換える (CRC が合わなくなる)
+        let at = png.windows(4).position(|w| w == b"Home").unwrap();
+        png[at] = b'h';
+        fs::write(&path, &png).unwrap();
+        assert!(read_metadata(&path).is_err());
+    }
+}
@@ -1,35 +1,336 @@
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    routing::get,
+    body::{Body, Bytes},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        DefaultBodyLimit, FromRef, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
     Json, Router,
 };
-use local_ip_address::local_ip;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio_stream::wrappers::{BroadcastStream, ReceiverStream};
+use tokio_stream::{Stream, StreamExt};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
 
-use super::db::LogDatabase;
-use super::watcher::Payload;
+use super::auth;
+use super::db::{
+    DbStats, ExpiredCursorError, InsertOutcome, InsertSummary, LogDatabase, LogFilter, LogPage,
+    LogRecord,
+};
+use super::discovery::{self, Advertisement, Advertiser};
+use super::hub::EventHub;
+use super::metrics::{self, metrics};
+use super::migrations;
+use super::network::{self, BindMode, BindTarget};
+use super::tls::TlsIdentity;
+use super::watcher::{LogSource, Payload, VrcLogEventKind, WatcherMonitor, WatcherStatus};
 
 const SERVER_PORT: u16 = 8727;
+/// Page size for /logs when `limit` is not specified.
+const DEFAULT_PAGE_SIZE: u32 = 1000;
+/// Upper bound for `limit` on /logs.
+const MAX_PAGE_SIZE: u32 = 5000;
+/// Number of events read from the DB at a time while streaming /logs.
+const STREAM_CHUNK_SIZE: u32 = 500;
+/// Settings key of the HTTPS switch
+const TLS_ENABLED_KEY: &str = "tls_enabled";
+/// Max number of events per POST /ingest request.
+const MAX_INGEST_BATCH: usize = 5000;
+/// Max request body size of POST /ingest.
+const MAX_INGEST_BODY: usize = 16 * 1024 * 1024;
+/// Max length of a `machine_id`.
+const MAX_MACHINE_ID_LEN: usize = 64;
+/// Upper bound for `replay` on the live event streams.
+const MAX_REPLAY: u32 = 500;
+
+/// Shared state of the HTTP server
+#[derive(Clone)]
+struct AppState {
+    db: LogDatabase,
+    hub: EventHub,
+    watcher: WatcherMonitor,
+}
+
+impl FromRef<AppState> for LogDatabase {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+/// Parse a comma separated list of event kinds.
+/// Returns None if it contains an unknown kind.
+fn parse_kinds(types: &str) -> Option<Vec<VrcLogEventKind>> {
+    types
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(VrcLogEventKind::from_name)
+        .collect()
+}
 
 /// Query parameters for the /logs endpoint
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct LogParams {
     /// Get logs occurred after this timestamp.
     /// Optional: if missing, returns all logs (or you can set a default limit).
     start: Option<String>,
     end: Option<String>,
+    /// Comma separated list of event kinds (e.g. `PlayerJoin,PlayerLeft`).
+    types: Option<String>,
+    /// Only events about this user (usr_xxx).
+    user_id: Option<String>,
+    /// Only events that happened while in this world (wrld_xxx), judged by the
+    /// latest instance joined before each event in the same log file.
+    world_id: Option<String>,
+    /// Partial match on player / user / world names.
+    q: Option<String>,
+    /// Max number of events per page (default 1000, max 5000).
+    limit: Option<u32>,
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+}
+
+impl LogParams {
+    /// Convert query parameters into a DB filter.
+    /// Returns None if `types` contains an unknown event kind.
+    fn to_filter(&self) -> Option<LogFilter> {
+        let kinds = match &self.types {
+            Some(types) => Some(parse_kinds(types)?),
+            None => None,
+        };
+        Some(LogFilter {
+            kinds,
+            user_id: self.user_id.clone(),
+            world_id: self.world_id.clone(),
+            text: self.q.clone(),
+        })
+    }
 }
 
 /// Handler for GET /logs
+/// The body is streamed in chunks so that memory use does not grow with the page size.
+/// The weak ETag is the newest row id of the whole database plus a generation that is bumped
+/// when stored rows are changed or deleted. Both are cheap to read, so any change to the logs
+/// (even outside the requested range) invalidates it rather than counting the filtered rows.
+#[utoipa::path(
+    get,
+    path = "/logs",
+    tag = "logs",
+    params(
+        LogParams,
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a previous response"),
+    ),
+    responses(
+        (status = 200, description = "One page of events in timestamp order", body = LogPage,
+            headers(("ETag" = String, description = "Changes when any event is added, changed or deleted"))),
+        (status = 304, description = "Not modified since the given ETag"),
+        (status = 400, description = "Unknown event kind or invalid cursor"),
+        (status = 401, description = "Missing or invalid pairing token"),
+        (status = 410, description = "The cursor points to deleted logs; fetch from the first page again"),
+    )
+)]
 async fn handle_get_logs(
     State(db): State<LogDatabase>,
     Query(params): Query<LogParams>,
-) -> Result<Json<Vec<Payload>>, StatusCode> {
-    match db.get_logs(params.start.as_deref(), params.end.as_deref()) {
-        Ok(logs) => Ok(Json(logs)),
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let filter = params.to_filter().ok_or(StatusCode::BAD_REQUEST)?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    if params
+        .cursor
+        .as_deref()
+        .is_some_and(|c| c.parse::<i64>().is_err())
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (latest_id, generation) = db.log_version().map_err(|e| {
+        eprintln!("Failed to fetch logs from DB: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let etag = format!("W/\"{}-{}\"", latest_id, generation);
+    if etag_matches(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    // Fetch the first chunk here so that DB errors still produce a proper status code
+    let first = db
+        .get_logs(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            &filter,
+            Some(limit.min(STREAM_CHUNK_SIZE)),
+            params.cursor.as_deref(),
+        )
+        .map_err(|e| {
+            if e.is::<ExpiredCursorError>() {
+                return StatusCode::GONE;
+            }
+            eprintln!("Failed to fetch logs from DB: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let body = stream_log_page(db, params, filter, limit, first);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+            (header::ETAG, etag),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Whether `If-None-Match` contains the ETag (weak comparison)
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = strip(etag);
+    value
+        .split(',')
+        .any(|tag| tag.trim() == "*" || strip(tag) == etag)
+}
+
+/// Serialize a LogPage chunk by chunk.
+/// The remaining chunks are read on a blocking thread, and the bounded channel keeps
+/// at most a few chunks in memory when the client reads slowly.
+fn stream_log_page(
+    db: LogDatabase,
+    params: LogParams,
+    filter: LogFilter,
+    limit: u32,
+    first: LogPage,
+) -> Body {
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(4);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut buf = b"{\"items\":[".to_vec();
+        let mut written = 0u32;
+        let mut page = first;
+        loop {
+            for item in &page.items {
+                if written > 0 {
+                    buf.push(b',');
+                }
+                if let Err(e) = serde_json::to_writer(&mut buf, item) {
+                    let _ = tx.blocking_send(Err(std::io::Error::other(e)));
+                    return;
+                }
+                written += 1;
+            }
+            if page.next_cursor.is_none() || written >= limit {
+                break;
+            }
+            if tx
+                .blocking_send(Ok(Bytes::from(std::mem::take(&mut buf))))
+                .is_err()
+            {
+                // The client disconnected
+                return;
+            }
+            page = match db.get_logs(
+                params.start.as_deref(),
+                params.end.as_deref(),
+                &filter,
+                Some((limit - written).min(STREAM_CHUNK_SIZE)),
+                page.next_cursor.as_deref(),
+            ) {
+                Ok(page) => page,
+                Err(e) => {
+                    eprintln!("Failed to fetch logs from DB: {}", e);
+                    // Abort the body so that the client does not take a truncated page as complete
+                    let _ = tx.blocking_send(Err(std::io::Error::other(e.to_string())));
+                    return;
+                }
+            };
+        }
+        buf.extend_from_slice(b"],\"next_cursor\":");
+        if let Err(e) = serde_json::to_writer(&mut buf, &page.next_cursor) {
+            let _ = tx.blocking_send(Err(std::io::Error::other(e)));
+            return;
+        }
+        buf.push(b'}');
+        let _ = tx.blocking_send(Ok(Bytes::from(buf)));
+    });
+
+    Body::from_stream(ReceiverStream::new(rx))
+}
+
+/// Query parameters for the /sync endpoint
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SyncParams {
+    /// Return events stored after this row id (0 or missing = from the beginning).
+    since_id: Option<i64>,
+    /// Max number of events per response (default 1000, max 5000).
+    limit: Option<u32>,
+}
+
+/// Response of the /sync endpoint
+#[derive(Serialize, ToSchema)]
+struct SyncResponse {
+    /// Events in insertion order.
+    events: Vec<LogRecord>,
+    /// Largest row id the client has now seen. Pass it as `since_id` next time.
+    high_water_mark: i64,
+    /// True if more events are available (call again immediately).
+    has_more: bool,
+}
+
+/// Handler for GET /sync
+/// Delta sync keyed by row id, which (unlike timestamps) is unique and strictly increasing.
+#[utoipa::path(
+    get,
+    path = "/sync",
+    tag = "logs",
+    params(SyncParams),
+    responses(
+        (status = 200, description = "Events stored after `since_id`", body = SyncResponse),
+        (status = 401, description = "Missing or invalid pairing token"),
+    )
+)]
+async fn handle_sync(
+    State(db): State<LogDatabase>,
+    Query(params): Query<SyncParams>,
+) -> Result<Json<SyncResponse>, StatusCode> {
+    let since_id = params.since_id.unwrap_or(0);
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    match db.get_logs_since(since_id, limit) {
+        Ok(events) => Ok(Json(SyncResponse {
+            high_water_mark: events.last().map_or(since_id, |e| e.id),
+            has_more: events.len() == limit as usize,
+            events,
+        })),
         Err(e) => {
             eprintln!("Failed to fetch logs from DB: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -37,41 +338,849 @@ async fn handle_get_logs(
     }
 }
 
-/// Start the HTTP server in a background task
-pub fn spawn_server(db: LogDatabase) {
-    tauri::async_runtime::spawn(async move {
-        let port_str = db.get_setting("port").unwrap_or(SERVER_PORT.to_string());
-        let port: u16 = port_str.parse().unwrap_or(SERVER_PORT);
-        // Build the application router
-        let app = Router::new()
+/// One event pushed to /ingest
+#[derive(Deserialize, ToSchema)]
+struct IngestEvent {
+    #[serde(flatten)]
+    payload: Payload,
+    /// Log file name on the sending PC (used with `source_offset` to drop duplicates).
+    /// Must be sent together with `source_offset`.
+    source_file: Option<String>,
+    /// Byte offset of the line in `source_file`.
+    source_offset: Option<u64>,
+}
+
+/// Request body of POST /ingest
+#[derive(Deserialize, ToSchema)]
+struct IngestRequest {
+    /// Stable id of the sending PC (its `machine_id`).
+    machine_id: String,
+    events: Vec<IngestEvent>,
+}
+
+/// Response of POST /ingest
+#[derive(Serialize, ToSchema)]
+struct IngestResponse {
+    /// Number of newly stored events.
+    inserted: usize,
+    /// Number of events that were already stored.
+    skipped: usize,
+}
+
+/// Handler for POST /ingest
+/// Stores events recorded on another PC, so that this instance holds the history of all PCs.
+/// Resending the same batch is safe: events are deduplicated per machine and source position.
+#[utoipa::path(
+    post,
+    path = "/ingest",
+    tag = "logs",
+    request_body = IngestRequest,
+    responses(
+        (status = 200, description = "Events were stored", body = IngestResponse),
+        (status = 400, description = "Invalid machine_id, too many events, or a source_file without source_offset"),
+        (status = 401, description = "Missing or invalid pairing token"),
+    )
+)]
+async fn handle_ingest(
+    State(state): State<AppState>,
+    Json(request): Json<IngestRequest>,
+) -> Result<Json<IngestResponse>, (StatusCode, String)> {
+    let machine_id = request.machine_id.trim();
+    if machine_id.is_empty() || machine_id.len() > MAX_MACHINE_ID_LEN {
+        return Err((StatusCode::BAD_REQUEST, "Invalid machine_id".to_string()));
+    }
+    // Events of this PC are recorded by its own watcher
+    if machine_id == state.db.machine_id() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "machine_id is the id of this PC".to_string(),
+        ));
+    }
+    if request.events.len() > MAX_INGEST_BATCH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Too many events (max {})", MAX_INGEST_BATCH),
+        ));
+    }
+
+    let entries = request
+        .events
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| {
+            // Without an offset every line of the file would share one dedupe key
+            let source = match (e.source_file, e.source_offset) {
+                (Some(file), Some(offset)) => Some(LogSource { file, offset }),
+                (None, None) => None,
+                _ => {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "events[{}]: source_file and source_offset must be sent together",
+                            i
+                        ),
+                    ))
+                }
+            };
+            Ok((e.payload, source))
+        })
+        .collect::<Result<Vec<(Payload, Option<LogSource>)>, _>>()?;
+    let outcomes = state.db.ingest_logs(machine_id, &entries).map_err(|e| {
+        eprintln!("Failed to ingest logs: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })?;
+
+    let mut summary = InsertSummary::default();
+    for ((payload, _), outcome) in entries.into_iter().zip(outcomes) {
+        summary.add(outcome);
+        // Live clients receive ingested events as well
+        if let InsertOutcome::Inserted(id) = outcome {
+            state.hub.publish(LogRecord { id, payload });
+        }
+    }
+    Ok(Json(IngestResponse {
+        inserted: summary.inserted,
+        skipped: summary.skipped,
+    }))
+}
+
+/// Query parameters for the live event streams (/events/ws, /events/sse)
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct StreamParams {
+    /// Comma separated list of event kinds to receive (missing = all).
+    types: Option<String>,
+    /// Number of recent events to send right after connecting (default 0, max 500).
+    replay: Option<u32>,
+}
+
+/// Connection settings of a live event stream
+struct StreamSubscription {
+    kinds: Option<Vec<VrcLogEventKind>>,
+    /// Recent events to send first.
+    replay: Vec<LogRecord>,
+    receiver: tokio::sync::broadcast::Receiver<LogRecord>,
+}
+
+impl StreamSubscription {
+    /// Subscribe to the hub and load the events to replay.
+    /// `last_event_id` (SSE reconnection) takes precedence over `replay`.
+    fn open(
+        state: &AppState,
+        params: &StreamParams,
+        last_event_id: Option<i64>,
+    ) -> Result<Self, StatusCode> {
+        let kinds = match &params.types {
+            Some(types) => Some(parse_kinds(types).ok_or(StatusCode::BAD_REQUEST)?),
+            None => None,
+        };
+        // Subscribe before loading the replay so that nothing is lost in between.
+        let receiver = state.hub.subscribe();
+
+        let replay = match last_event_id {
+            Some(since_id) => state
+                .db
+                .get_logs_since(since_id, MAX_REPLAY)
+                .map(|records| {
+                    records
+                        .into_iter()
+                        .filter(|r| matches_kinds(&kinds, r))
+                        .collect()
+                }),
+            None => match params.replay.unwrap_or(0).min(MAX_REPLAY) {
+                0 => Ok(Vec::new()),
+                count => {
+                    let filter = LogFilter {
+                        kinds: kinds.clone(),
+                        ..Default::default()
+                    };
+                    state.db.get_recent_logs(&filter, count)
+                }
+            },
+        }
+        .map_err(|e| {
+            eprintln!("Failed to load events to replay: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        Ok(StreamSubscription {
+            kinds,
+            replay,
+            receiver,
+        })
+    }
+
+    /// Row id of the last replayed event (live events up to this id are duplicates).
+    fn replayed_up_to(&self) -> i64 {
+        self.replay.last().map_or(0, |r| r.id)
+    }
+}
+
+/// Whether the record passes the event kind filter.
+fn matches_kinds(kinds: &Option<Vec<VrcLogEventKind>>, record: &LogRecord) -> bool {
+    match kinds {
+        Some(kinds) if !kinds.is_empty() => kinds.contains(&record.payload.event.kind()),
+        _ => true,
+    }
+}
+
+/// Handler for GET /events/ws
+/// Pushes every stored event as a JSON text message.
+#[utoipa::path(
+    get,
+    path = "/events/ws",
+    tag = "events",
+    params(StreamParams),
+    responses(
+        (status = 101, description = "WebSocket upgrade. Each text message is a LogRecord", body = LogRecord),
+        (status = 400, description = "Unknown event kind"),
+        (status = 401, description = "Missing or invalid pairing token"),
+    )
+)]
+async fn handle_events_ws(
+    State(state): State<AppState>,
+    Query(params): Query<StreamParams>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let subscription = StreamSubscription::open(&state, &params, None)?;
+    Ok(ws.on_upgrade(move |socket| run_websocket(socket, subscription)))
+}
+
+async fn run_websocket(mut socket: WebSocket, mut subscription: StreamSubscription) {
+    let replayed_up_to = subscription.replayed_up_to();
+    for record in std::mem::take(&mut subscription.replay) {
+        if send_ws_record(&mut socket, &record).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            received = subscription.receiver.recv() => match received {
+                Ok(record) => {
+                    if record.id <= replayed_up_to || !matches_kinds(&subscription.kinds, &record) {
+                        continue;
+                    }
+                    if send_ws_record(&mut socket, &record).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    eprintln!("WebSocket client lagged behind, {} events skipped", skipped);
+                }
+                Err(RecvError::Closed) => return,
+            },
+            incoming = socket.recv() => match incoming {
+                // Ignore messages from the client, stop when it disconnects.
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn send_ws_record(socket: &mut WebSocket, record: &LogRecord) -> Result<(), axum::Error> {
+    match serde_json::to_string(record) {
+        Ok(json) => socket.send(Message::Text(json.into())).await,
+        Err(e) => {
+            eprintln!("Failed to serialize event: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Handler for GET /events/sse
+/// Pushes every stored event as a `log` event whose id is the row id,
+/// so that EventSource reconnections resume via `Last-Event-ID`.
+#[utoipa::path(
+    get,
+    path = "/events/sse",
+    tag = "events",
+    params(
+        StreamParams,
+        ("Last-Event-ID" = Option<i64>, Header, description = "Resume after this row id"),
+    ),
+    responses(
+        (status = 200, description = "Stream of `log` events whose data is a LogRecord", body = LogRecord, content_type = "text/event-stream"),
+        (status = 400, description = "Unknown event kind"),
+        (status = 401, description = "Missing or invalid pairing token"),
+    )
+)]
+async fn handle_events_sse(
+    State(state): State<AppState>,
+    Query(params): Query<StreamParams>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<i64>().ok());
+    let subscription = StreamSubscription::open(&state, &params, last_event_id)?;
+
+    let replayed_up_to = subscription.replayed_up_to();
+    let kinds = subscription.kinds;
+    let live = BroadcastStream::new(subscription.receiver).filter_map(move |received| {
+        // Lagged receivers just skip the missed events.
+        let record = received.ok()?;
+        (record.id > replayed_up_to && matches_kinds(&kinds, &record)).then_some(record)
+    });
+    let stream = tokio_stream::iter(subscription.replay)
+        .chain(live)
+        .filter_map(|record| {
+            Event::default()
+                .event("log")
+                .id(record.id.to_string())
+                .json_data(&record)
+                .ok()
+                .map(Ok)
+        });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Endpoints served by this version (listed on /info)
+const ENDPOINTS: &[&str] = &[
+    "/health",
+    "/info",
+    "/metrics",
+    "/logs",
+    "/sync",
+    "/ingest",
+    "/events/ws",
+    "/events/sse",
+    "/openapi.json",
+];
+/// Optional API features supported by this version (listed on /info)
+const FEATURES: &[&str] = &[
+    "logs.filter",
+    "logs.cursor",
+    "sync",
+    "events.websocket",
+    "events.sse",
+    "auth.bearer",
+    "compression",
+    "etag",
+    "ingest",
+];
+
+/// Response of GET /health
+#[derive(Serialize, ToSchema)]
+struct HealthResponse {
+    /// "ok", or "degraded" when the database or the watcher is not working
+    status: &'static str,
+    version: &'static str,
+    protocol_version: &'static str,
+    database: bool,
+    /// The watcher task is alive and has polled the log file recently
+    watcher: bool,
+}
+
+/// Handler for GET /health (does not require the pairing token)
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "server",
+    security(()),
+    responses(
+        (status = 200, description = "Server is healthy", body = HealthResponse),
+        (status = 503, description = "Database or watcher is not working", body = HealthResponse),
+    )
+)]
+async fn handle_health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let database = state.db.ping().is_ok();
+    let watcher_ok = state.watcher.is_healthy();
+    let healthy = database && watcher_ok;
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(HealthResponse {
+            status: if healthy { "ok" } else { "degraded" },
+            version: env!("CARGO_PKG_VERSION"),
+            protocol_version: discovery::PROTOCOL_VERSION,
+            database,
+            watcher: watcher_ok,
+        }),
+    )
+}
+
+/// Schema version of the database
+#[derive(Serialize, ToSchema)]
+struct SchemaInfo {
+    /// Version applied to the database
+    current: u32,
+    /// Latest version this app can handle
+    supported: u32,
+}
+
+/// Response of GET /info
+#[derive(Serialize, ToSchema)]
+struct InfoResponse {
+    name: &'static str,
+    version: &'static str,
+    protocol_version: &'static str,
+    /// Id of this PC (events recorded here are stored with it)
+    machine_id: String,
+    schema: SchemaInfo,
+    database: DbStats,
+    watcher: WatcherStatus,
+    #[schema(value_type = Vec<VrcLogEventKind>)]
+    event_kinds: Vec<&'static str>,
+    #[schema(value_type = Vec<String>)]
+    endpoints: &'static [&'static str],
+    #[schema(value_type = Vec<String>)]
+    features: &'static [&'static str],
+}
+
+/// Handler for GET /info
+#[utoipa::path(
+    get,
+    path = "/info",
+    tag = "server",
+    responses(
+        (status = 200, description = "Versions, database and watcher status", body = InfoResponse),
+        (status = 401, description = "Missing or invalid pairing token"),
+    )
+)]
+async fn handle_info(State(state): State<AppState>) -> Result<Json<InfoResponse>, StatusCode> {
+    let database = state.db.stats().map_err(|e| {
+        eprintln!("DB Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(InfoResponse {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        protocol_version: discovery::PROTOCOL_VERSION,
+        machine_id: state.db.machine_id().to_string(),
+        schema: SchemaInfo {
+            current: database.schema_version,
+            supported: migrations::latest_version(),
+        },
+        database,
+        watcher: state.watcher.snapshot(),
+        event_kinds: VrcLogEventKind::ALL.iter().map(|k| k.as_str()).collect(),
+        endpoints: ENDPOINTS,
+        features: FEATURES,
+    }))
+}
+
+/// Handler for GET /metrics (Prometheus text format)
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "server",
+    responses(
+        (status = 200, description = "Counters and gauges in the Prometheus text format", body = String, content_type = "text/plain; version=0.0.4"),
+        (status = 401, description = "Missing or invalid pairing token"),
+    )
+)]
+async fn handle_metrics(State(db): State<LogDatabase>) -> impl IntoResponse {
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        metrics().render(&db),
+    )
+}
+
+/// Adds the pairing token (Bearer) security scheme
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+/// OpenAPI document of the HTTP API consumed by the mobile app
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "VRCP Desktop API",
+        description = "LAN API of the VRCP desktop app. Pass the pairing token as `Authorization: Bearer <token>` (or `?token=`)."
+    ),
+    paths(
+        handle_health,
+        handle_info,
+        handle_metrics,
+        handle_get_logs,
+        handle_sync,
+        handle_ingest,
+        handle_events_ws,
+        handle_events_sse,
+    ),
+    modifiers(&BearerAuth),
+    security(("token" = [])),
+    tags(
+        (name = "server", description = "Server status"),
+        (name = "logs", description = "Stored VRChat log events"),
+        (name = "events", description = "Live event streams"),
+    )
+)]
+struct ApiDoc;
+
+/// OpenAPI document of the HTTP API (also served at /openapi.json)
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
+
+/// Handler for GET /openapi.json
+async fn handle_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(openapi())
+}
+
+/// Time to wait for open connections (e.g. live streams) to close on shutdown.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Port configured in the settings (default 8727)
+fn configured_port(db: &LogDatabase) -> u16 {
+    let port_str = db.get_setting("port").unwrap_or(SERVER_PORT.to_string());
+    port_str.parse().unwrap_or(SERVER_PORT)
+}
+
+/// Server tasks that are currently serving requests (one per bound address)
+struct RunningServer {
+    addrs: Vec<SocketAddr>,
+    shutdown: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+    /// TLS accept loops; they own the TCP listeners, so the port is only free once they end
+    accept_tasks: Vec<JoinHandle<()>>,
+    /// mDNS registration (None when not advertised)
+    advertisement: Option<Advertisement>,
+}
+
+impl RunningServer {
+    /// Stop accepting connections and wait for the tasks to finish.
+    async fn stop(self) {
+        if let Some(advertisement) = self.advertisement {
+            advertisement.withdraw();
+        }
+        let _ = self.shutdown.send(true);
+        for mut task in self.tasks {
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, &mut task)
+                .await
+                .is_err()
+            {
+                // Long-lived connections (WebSocket / SSE) do not end by themselves
+                task.abort();
+            }
+        }
+        for task in self.accept_tasks {
+            task.abort();
+            let _ = task.await;
+        }
+        println!("HTTP Server on {:?} stopped", self.addrs);
+    }
+}
+
+/// Whether HTTPS is enabled in the settings (default true)
+fn tls_enabled(db: &LogDatabase) -> bool {
+    db.get_setting(TLS_ENABLED_KEY)
+        .map(|v| v != "false")
+        .unwrap_or(true)
+}
+
+/// Owner of the HTTP server tasks.
+/// Allows rebinding in place (e.g. after a port or bind mode change) without restarting the app.
+#[derive(Clone)]
+pub struct ServerHandle {
+    state: AppState,
+    /// Self-signed certificate (None if it could not be created; serves plain HTTP)
+    tls: Option<TlsIdentity>,
+    /// mDNS responder (None if it could not be started)
+    advertiser: Option<Advertiser>,
+    running: Arc<Mutex<Option<RunningServer>>>,
+}
+
+impl ServerHandle {
+    pub fn new(
+        db: LogDatabase,
+        hub: EventHub,
+        watcher: WatcherMonitor,
+        tls: Option<TlsIdentity>,
+    ) -> Self {
+        let advertiser = Advertiser::new()
+            .inspect_err(|e| eprintln!("Failed to start mDNS responder: {}", e))
+            .ok();
+        ServerHandle {
+            state: AppState { db, hub, watcher },
+            tls,
+            advertiser,
+            running: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn db(&self) -> &LogDatabase {
+        &self.state.db
+    }
+
+    /// Certificate used when HTTPS is enabled
+    fn active_tls(&self) -> Option<&TlsIdentity> {
+        self.tls.as_ref().filter(|_| tls_enabled(&self.state.db))
+    }
+
+    /// SHA-256 fingerprint of the certificate (None when serving plain HTTP)
+    pub fn fingerprint(&self) -> Option<String> {
+        self.active_tls().map(|tls| tls.fingerprint.clone())
+    }
+
+    /// URL for connecting to this server (used for the QR code)
+    pub fn url(&self) -> Result<String, String> {
+        let db = &self.state.db;
+        let ip = BindMode::load(db).advertised_ip(db)?;
+        let scheme = if self.active_tls().is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        Ok(format!(
+            "{}://{}",
+            scheme,
+            SocketAddr::new(ip, configured_port(db))
+        ))
+    }
+
+    /// Build the application router
+    fn router(&self) -> Router {
+        Router::new()
             .route("/logs", get(handle_get_logs))
-            .with_state(db) // Share the DB instance with handlers
-            .layer(CorsLayer::permissive()); // Allow access from Mobile (different IP)
+            .route("/sync", get(handle_sync))
+            .route(
+                "/ingest",
+                post(handle_ingest).layer(DefaultBodyLimit::max(MAX_INGEST_BODY)),
+            )
+            .route("/events/ws", get(handle_events_ws))
+            .route("/events/sse", get(handle_events_sse))
+            .route("/info", get(handle_info))
+            .route("/metrics", get(handle_metrics))
+            // Every route above requires the pairing token
+            .route_layer(middleware::from_fn_with_state(
+                self.state.db.clone(),
+                auth::require_token,
+            ))
+            // Lets unpaired clients check that this is a compatible VRCP
+            .route("/health", get(handle_health))
+            .route("/openapi.json", get(handle_openapi))
+            // Count requests per route (covers the routes above, including rejected ones)
+            .route_layer(middleware::from_fn(metrics::track_http))
+            .with_state(self.state.clone()) // Share the DB and event hub with handlers
+            .layer(CorsLayer::permissive()) // Allow access from Mobile (different IP)
+            .layer(CompressionLayer::new()) // gzip / br, negotiated by Accept-Encoding
+    }
 
-        // Listen on 0.0.0.0 to accept connections from LAN (Mobile)
-        let addr = SocketAddr::from(([0, 0, 0, 0], port));
-        println!("HTTP Server listening on http://{}", addr);
+    /// (Re)start the server with the current settings (port and bind mode).
+    /// If the new addresses cannot be bound, the previous addresses are restored
+    /// and the bind error is returned.
+    pub async fn restart(&self) -> Result<Vec<SocketAddr>, String> {
+        let mut running = self.running.lock().await;
+
+        // Stop the current server first so that the same port can be bound again
+        let previous_addrs = match running.take() {
+            Some(server) => {
+                let addrs = server.addrs.clone();
+                server.stop().await;
+                addrs
+            }
+            None => Vec::new(),
+        };
+
+        let db = &self.state.db;
+        let bound = BindMode::load(db)
+            .bind_targets(configured_port(db))
+            .and_then(|targets| bind_all(&targets));
+        match bound {
+            Ok(listeners) => {
+                let addrs = listeners.iter().map(|(_, addr)| *addr).collect();
+                *running = Some(self.serve(listeners));
+                Ok(addrs)
+            }
+            Err(message) => {
+                eprintln!("{}", message);
+                if !previous_addrs.is_empty() {
+                    let targets: Vec<BindTarget> = previous_addrs
+                        .into_iter()
+                        .map(|addr| BindTarget {
+                            addr,
+                            required: false,
+                        })
+                        .collect();
+                    match bind_all(&targets) {
+                        Ok(listeners) => *running = Some(self.serve(listeners)),
+                        Err(e) => eprintln!("Failed to restore server: {}", e),
+                    }
+                }
+                Err(message)
+            }
+        }
+    }
+
+    /// Register the server on mDNS with the current settings
+    fn advertise(&self, port: u16) -> Option<Advertisement> {
+        let advertiser = self.advertiser.as_ref()?;
+        advertiser
+            .advertise(
+                &BindMode::load(&self.state.db),
+                port,
+                self.active_tls().is_some(),
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to advertise HTTP Server over mDNS: {}", e);
+                None
+            })
+    }
 
-        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-        axum::serve(listener, app).await.unwrap();
+    fn serve(&self, listeners: Vec<(TcpListener, SocketAddr)>) -> RunningServer {
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let addrs: Vec<SocketAddr> = listeners.iter().map(|(_, addr)| *addr).collect();
+        let advertisement = addrs.first().and_then(|addr| self.advertise(addr.port()));
+        let tls = self.active_tls();
+        let mut tasks = Vec::new();
+        let mut accept_tasks = Vec::new();
+        for (listener, addr) in listeners {
+            let app = self.router();
+            let mut shutdown_rx = shutdown_rx.clone();
+            let shutdown_signal = async move {
+                let _ = shutdown_rx.wait_for(|stop| *stop).await;
+            };
+            let task = match tls {
+                Some(tls) => match tls.listener(listener) {
+                    Ok((listener, accept_task)) => {
+                        accept_tasks.push(accept_task);
+                        println!("HTTP Server listening on https://{}", addr);
+                        tauri::async_runtime::spawn(async move {
+                            let result = axum::serve(listener, app)
+                                .with_graceful_shutdown(shutdown_signal)
+                                .await;
+                            if let Err(e) = result {
+                                eprintln!("HTTP Server error on {}: {}", addr, e);
+                            }
+                        })
+                    }
+                    Err(e) => {
+                        eprintln!("HTTP Server error on {}: {}", addr, e);
+                        continue;
+                    }
+                },
+                None => {
+                    println!("HTTP Server listening on http://{}", addr);
+                    tauri::async_runtime::spawn(async move {
+                        let result = axum::serve(listener, app)
+                            .with_graceful_shutdown(shutdown_signal)
+                            .await;
+                        if let Err(e) = result {
+                            eprintln!("HTTP Server error on {}: {}", addr, e);
+                        }
+                    })
+                }
+            };
+            tasks.push(task);
+        }
+        RunningServer {
+            addrs,
+            shutdown,
+            tasks,
+            accept_tasks,
+            advertisement,
+        }
+    }
+}
+
+/// Bind every target. Fails if a required address cannot be bound
+/// or nothing could be bound at all.
+fn bind_all(targets: &[BindTarget]) -> Result<Vec<(TcpListener, SocketAddr)>, String> {
+    let mut listeners = Vec::new();
+    for target in targets {
+        match network::bind_listener(target.addr) {
+            Ok(listener) => listeners.push((listener, target.addr)),
+            Err(e) if target.required => {
+                return Err(format!("Failed to bind {}: {}", target.addr, e));
+            }
+            Err(e) => eprintln!("Skipped binding {}: {}", target.addr, e),
+        }
+    }
+    if listeners.is_empty() {
+        return Err("No address could be bound".to_string());
+    }
+    Ok(listeners)
+}
+
+/// Start the HTTP server in a background task
+pub fn spawn_server(
+    db: LogDatabase,
+    hub: EventHub,
+    watcher: WatcherMonitor,
+    tls: Option<TlsIdentity>,
+) -> ServerHandle {
+    if let Err(e) = auth::ensure_token(&db) {
+        eprintln!("Failed to issue pairing token: {}", e);
+    }
+    let handle = ServerHandle::new(db, hub, watcher, tls);
+    let server = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = server.restart().await {
+            eprintln!("Failed to start HTTP Server: {}", e);
+        }
     });
+    handle
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn get_server_url(db: tauri::State<'_, LogDatabase>) -> Result<String, String> {
-    let ip = local_ip().map_err(|e| e.to_string())?;
+pub fn get_server_url(server: tauri::State<'_, ServerHandle>) -> Result<String, String> {
+    server.url()
+}
 
-    let port_str = db.get_setting("port").unwrap_or(SERVER_PORT.to_string());
-    let port: u16 = port_str.parse().unwrap_or(SERVER_PORT);
-    Ok(format!("http://{}:{}", ip, port))
+#[tauri::command]
+#[specta::specta]
+pub fn get_tls_enabled(server: tauri::State<'_, ServerHandle>) -> Result<bool, String> {
+    Ok(server.active_tls().is_some())
 }
 
 #[tauri::command]
 #[specta::specta]
-pub fn set_server_port(
-    app: tauri::AppHandle,
+pub async fn set_tls_enabled(
+    server: tauri::State<'_, ServerHandle>,
+    enabled: bool,
+) -> Result<(), String> {
+    if enabled && server.tls.is_none() {
+        return Err("The certificate for HTTPS is not available".to_string());
+    }
+    let db = server.db();
+    let previous = tls_enabled(db);
+    db.set_setting(TLS_ENABLED_KEY, &enabled.to_string())
+        .map_err(|e| e.to_string())?;
+
+    // 再起動できなければ元の設定に戻す
+    if let Err(e) = server.restart().await {
+        db.set_setting(TLS_ENABLED_KEY, &previous.to_string())
+            .map_err(|e| e.to_string())?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// SHA-256 fingerprint of the server certificate, for pinning on the mobile side
+#[tauri::command]
+#[specta::specta]
+pub fn get_certificate_fingerprint(
+    server: tauri::State<'_, ServerHandle>,
+) -> Result<Option<String>, String> {
+    Ok(server.fingerprint())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn set_server_port(
     db: tauri::State<'_, LogDatabase>,
+    server: tauri::State<'_, ServerHandle>,
     port: u16,
 ) -> Result<(), String> {
     // 1. バリデーション (u16なので 0~65535 は保証されるが、0番ポートなどを弾くならここに書く)
@@ -81,18 +1190,131 @@ pub fn set_server_port(
 
     // 2. DBに保存 (文字列として保存)
     // map_err で DBのエラーを文字列化してフロントエンドに返せるようにする
+    let previous = configured_port(&db);
     db.set_setting("port", &port.to_string())
         .map_err(|e| e.to_string())?;
 
-    // 3. restart
-    app.restart();
-    // Ok(())
+    // 3. サーバーだけを再起動 (watcher は止めない)
+    if let Err(e) = server.restart().await {
+        // bind できなければ元のポートに戻す
+        db.set_setting("port", &previous.to_string())
+            .map_err(|e| e.to_string())?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_bind_mode(db: tauri::State<'_, LogDatabase>) -> Result<BindMode, String> {
+    Ok(BindMode::load(&db))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn set_bind_mode(
+    db: tauri::State<'_, LogDatabase>,
+    server: tauri::State<'_, ServerHandle>,
+    mode: BindMode,
+) -> Result<(), String> {
+    // 指定アドレスの形式チェック
+    mode.bind_targets(configured_port(&db))?;
+
+    let previous = BindMode::load(&db);
+    mode.save(&db)?;
+
+    // bind できなければ元の設定に戻す
+    if let Err(e) = server.restart().await {
+        previous.save(&db)?;
+        return Err(e);
+    }
+    Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn get_server_port(db: tauri::State<'_, LogDatabase>) -> Result<u16, String> {
-    let port_str = db.get_setting("port").unwrap_or(SERVER_PORT.to_string());
-    let port: u16 = port_str.parse().unwrap_or(SERVER_PORT);
-    Ok(port)
+    Ok(configured_port(&db))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(watcher: WatcherMonitor) -> (tempfile::TempDir, AppState) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().to_path_buf()).unwrap();
+        let state = AppState {
+            db,
+            hub: EventHub::new(),
+            watcher,
+        };
+        (dir, state)
+    }
+
+    #[test]
+    fn health_is_degraded_once_watcher_task_ends() {
+        let watcher = WatcherMonitor::new();
+        let running = watcher.start();
+        let (_dir, state) = state(watcher);
+
+        let (status, Json(health)) =
+            tauri::async_runtime::block_on(handle_health(State(state.clone())));
+        assert_eq!(status, StatusCode::OK);
+        assert!(health.watcher);
+
+        drop(running);
+        let (status, Json(health)) = tauri::async_runtime::block_on(handle_health(State(state)));
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(health.status, "degraded");
+        assert!(!health.watcher);
+    }
+
+    fn ingest(state: &AppState, body: serde_json::Value) -> Result<IngestResponse, StatusCode> {
+        let request = serde_json::from_value(body).unwrap();
+        tauri::async_runtime::block_on(handle_ingest(State(state.clone()), Json(request)))
+            .map(|Json(response)| response)
+            .map_err(|(status, _)| status)
+    }
+
+    fn join_event(name: &str, offset: Option<u64>) -> serde_json::Value {
+        let mut event = serde_json::json!({
+            "event": {
+                "type": "PlayerJoin",
+                "data": { "player_name": name, "user_id": format!("usr_{}", name) },
+            },
+            "timestamp": "2024-01-01 10:00:00",
+            "source_file": "output_log_2024-01-01_10-00-00.txt",
+        });
+        if let Some(offset) = offset {
+            event["source_offset"] = offset.into();
+        }
+        event
+    }
+
+    #[test]
+    fn ingest_rejects_source_file_without_offset() {
+        let (_dir, state) = state(WatcherMonitor::new());
+        let status = ingest(
+            &state,
+            serde_json::json!({
+                "machine_id": "other-pc",
+                "events": [join_event("alice", Some(0)), join_event("bob", None)],
+            }),
+        )
+        .err();
+        assert_eq!(status, Some(StatusCode::BAD_REQUEST));
+        // バッチ全体を拒否し、一部だけ保存したりしない
+        assert_eq!(state.db.stats().unwrap().total_logs, 0);
+
+        let response = ingest(
+            &state,
+            serde_json::json!({
+                "machine_id": "other-pc",
+                "events": [join_event("alice", Some(0)), join_event("bob", Some(100))],
+            }),
+        )
+        .unwrap();
+        assert_eq!((response.inserted, response.skipped), (2, 0));
+    }
 }
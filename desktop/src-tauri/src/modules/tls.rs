@@ -0,0 +1,150 @@
+use axum::serve::Listener;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{self, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+/// 証明書を保存するディレクトリ名 (app data dir 配下)
+const TLS_DIR: &str = "tls";
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+/// TLSハンドシェイクのタイムアウト
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTPSサーバー用の自己署名証明書
+/// モバイルアプリはCAではなく `fingerprint` を固定 (ピン留め) して検証する
+#[derive(Clone)]
+pub struct TlsIdentity {
+    acceptor: TlsAcceptor,
+    /// 証明書(DER)の SHA-256 (AA:BB:... 形式)
+    pub fingerprint: String,
+}
+
+impl TlsIdentity {
+    /// 保存済みの証明書を読み込む。なければ生成して保存する
+    pub fn load_or_create(app_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let dir = app_dir.join(TLS_DIR);
+        let cert_path = dir.join(CERT_FILE);
+        let key_path = dir.join(KEY_FILE);
+
+        if !cert_path.exists() || !key_path.exists() {
+            fs::create_dir_all(&dir)?;
+            let certified = rcgen::generate_simple_self_signed(vec![
+                "vrcp.local".to_string(),
+                "localhost".to_string(),
+            ])?;
+            fs::write(&cert_path, certified.cert.pem())?;
+            fs::write(&key_path, certified.key_pair.serialize_pem())?;
+            println!("Generated self-signed certificate: {:?}", cert_path);
+        }
+
+        let cert = CertificateDer::from_pem_file(&cert_path)?;
+        let key = PrivateKeyDer::from_pem_file(&key_path)?;
+        let fingerprint = fingerprint(&cert);
+
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_no_client_auth()
+                .with_single_cert(vec![cert], key)?;
+
+        Ok(TlsIdentity {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+            fingerprint,
+        })
+    }
+
+    /// TCP listener を TLS で包む
+    /// 返り値の JoinHandle は listener を所有する accept タスク (停止時に終了を待つ)
+    pub fn listener(&self, listener: TcpListener) -> io::Result<(TlsListener, JoinHandle<()>)> {
+        TlsListener::new(listener, self.acceptor.clone())
+    }
+}
+
+/// 証明書(DER)の SHA-256 フィンガープリント
+pub(crate) fn fingerprint(cert: &CertificateDer) -> String {
+    Sha256::digest(cert.as_ref())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// TLSハンドシェイク済みの接続を axum に渡す Listener
+/// ハンドシェイクは接続ごとに別タスクで行い、遅いクライアントが accept を詰まらせないようにする
+pub struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    fn new(listener: TcpListener, acceptor: TlsAcceptor) -> io::Result<(Self, JoinHandle<()>)> {
+        let local_addr = listener.local_addr()?;
+        let (tx, incoming) = mpsc::channel(64);
+
+        let accept_task = tauri::async_runtime::spawn(async move {
+            loop {
+                let (stream, addr) = tokio::select! {
+                    // TlsListener が破棄されたら (サーバー停止) ソケットを閉じる
+                    _ = tx.closed() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            eprintln!("Failed to accept connection: {}", e);
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            continue;
+                        }
+                    },
+                };
+
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tauri::async_runtime::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(tls)) => {
+                            let _ = tx.send((tls, addr)).await;
+                        }
+                        Ok(Err(e)) => eprintln!("TLS handshake with {} failed: {}", addr, e),
+                        Err(_) => eprintln!("TLS handshake with {} timed out", addr),
+                    }
+                });
+            }
+        });
+
+        Ok((
+            TlsListener {
+                incoming,
+                local_addr,
+            },
+            accept_task,
+        ))
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(accepted) => accepted,
+            // accept タスクが終了した場合は新しい接続を待たない
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
@@ -1,22 +1,29 @@
 use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
-use std::sync::OnceLock;
-use std::time::Duration;
-use tauri::AppHandle;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
 use tauri_specta::Event;
+use utoipa::ToSchema;
 
-use crate::modules::db::LogDatabase;
+use crate::modules::backfill;
+use crate::modules::db::{InsertOutcome, LogCheckpoint, LogDatabase, LogRecord};
+use crate::modules::forwarder::{ForwardMode, Forwarder};
+use crate::modules::hub::EventHub;
+use crate::modules::metrics::metrics;
+use crate::modules::screenshot;
 
 // ================================================================
 // Section A: Data Types & Parsing Logic
 // (イベント定義や正規表現など、データの「中身」に関する処理)
 // ================================================================
 
-#[derive(Clone, Serialize, Debug, Type, Event, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Debug, Type, Event, Deserialize, ToSchema)]
 #[serde(tag = "type", content = "data")]
 pub enum VrcLogEvent {
     AppStart,
@@ -41,14 +48,134 @@ pub enum VrcLogEvent {
         user_id: String,
     },
     SelfLeft,
+    /// ワールドの動画プレイヤーがURLを読み込んだ
+    VideoPlay {
+        url: String,
+        /// 実際に再生されるURL (YouTube などを解決した後)
+        resolved_url: Option<String>,
+        /// 動画をリクエストしたプレイヤー (プレイヤーが出力している場合のみ)
+        requested_by: Option<String>,
+    },
+    /// プレイヤー (自分を含む) がアバターを変更した
+    AvatarChange {
+        player_name: String,
+        /// ログには名前しか出力されないため、同じインスタンスにいるプレイヤーから補完する
+        user_id: Option<String>,
+        avatar_name: String,
+    },
+    /// VRChat のカメラで写真を撮った
+    Screenshot {
+        /// 保存先のフルパス
+        path: String,
+    },
+    /// ワールドが外部のURLからデータを読み込んだ (VRCStringDownloader / VRCImageDownloader)
+    RemoteContentLoad {
+        kind: RemoteContentKind,
+        url: String,
+    },
+}
+
+/// 外部から読み込んだデータの種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Type, ToSchema)]
+pub enum RemoteContentKind {
+    String,
+    Image,
 }
 
-#[derive(Clone, Serialize, Deserialize, Type, Event)]
+/// VrcLogEvent の種別 (serde の `type` タグと同じ名前)
+/// DBの event_type カラムやフィルタ条件に使う
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Type, ToSchema)]
+pub enum VrcLogEventKind {
+    AppStart,
+    AppStop,
+    Login,
+    WorldEnter,
+    InstanceJoin,
+    PlayerJoin,
+    PlayerLeft,
+    SelfLeft,
+    VideoPlay,
+    AvatarChange,
+    Screenshot,
+    RemoteContentLoad,
+}
+
+impl VrcLogEventKind {
+    /// 全種別
+    pub const ALL: &'static [VrcLogEventKind] = &[
+        VrcLogEventKind::AppStart,
+        VrcLogEventKind::AppStop,
+        VrcLogEventKind::Login,
+        VrcLogEventKind::WorldEnter,
+        VrcLogEventKind::InstanceJoin,
+        VrcLogEventKind::PlayerJoin,
+        VrcLogEventKind::PlayerLeft,
+        VrcLogEventKind::SelfLeft,
+        VrcLogEventKind::VideoPlay,
+        VrcLogEventKind::AvatarChange,
+        VrcLogEventKind::Screenshot,
+        VrcLogEventKind::RemoteContentLoad,
+    ];
+
+    /// DBに保存する名前 (`type` タグと一致する)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VrcLogEventKind::AppStart => "AppStart",
+            VrcLogEventKind::AppStop => "AppStop",
+            VrcLogEventKind::Login => "Login",
+            VrcLogEventKind::WorldEnter => "WorldEnter",
+            VrcLogEventKind::InstanceJoin => "InstanceJoin",
+            VrcLogEventKind::PlayerJoin => "PlayerJoin",
+            VrcLogEventKind::PlayerLeft => "PlayerLeft",
+            VrcLogEventKind::SelfLeft => "SelfLeft",
+            VrcLogEventKind::VideoPlay => "VideoPlay",
+            VrcLogEventKind::AvatarChange => "AvatarChange",
+            VrcLogEventKind::Screenshot => "Screenshot",
+            VrcLogEventKind::RemoteContentLoad => "RemoteContentLoad",
+        }
+    }
+
+    /// 名前から種別を取得
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.as_str() == name)
+    }
+}
+
+impl VrcLogEvent {
+    /// イベントの種別
+    pub fn kind(&self) -> VrcLogEventKind {
+        match self {
+            VrcLogEvent::AppStart => VrcLogEventKind::AppStart,
+            VrcLogEvent::AppStop => VrcLogEventKind::AppStop,
+            VrcLogEvent::Login { .. } => VrcLogEventKind::Login,
+            VrcLogEvent::WorldEnter { .. } => VrcLogEventKind::WorldEnter,
+            VrcLogEvent::InstanceJoin { .. } => VrcLogEventKind::InstanceJoin,
+            VrcLogEvent::PlayerJoin { .. } => VrcLogEventKind::PlayerJoin,
+            VrcLogEvent::PlayerLeft { .. } => VrcLogEventKind::PlayerLeft,
+            VrcLogEvent::SelfLeft => VrcLogEventKind::SelfLeft,
+            VrcLogEvent::VideoPlay { .. } => VrcLogEventKind::VideoPlay,
+            VrcLogEvent::AvatarChange { .. } => VrcLogEventKind::AvatarChange,
+            VrcLogEvent::Screenshot { .. } => VrcLogEventKind::Screenshot,
+            VrcLogEvent::RemoteContentLoad { .. } => VrcLogEventKind::RemoteContentLoad,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, ToSchema)]
 pub struct Payload {
     pub event: VrcLogEvent,
     pub timestamp: String,
 }
 
+/// イベントの取り込み元 (重複排除のキーに使う)
+#[derive(Clone, Debug)]
+pub struct LogSource {
+    /// ログファイル名 (ディレクトリを含まない)
+    pub file: String,
+    /// 行の開始バイトオフセット
+    pub offset: u64,
+}
+
 struct LogDefinition {
     pattern_part: &'static str,
     factory: fn(&Captures) -> VrcLogEvent,
@@ -101,6 +228,62 @@ const LOG_DEFINITIONS: &[LogDefinition] = &[
         pattern_part: r"\[Behaviour\] OnLeftRoom",
         factory: |_| VrcLogEvent::SelfLeft,
     },
+    LogDefinition {
+        pattern_part: r"\[Behaviour\] Switching (.+?) to avatar (.+)",
+        factory: |caps| VrcLogEvent::AvatarChange {
+            player_name: caps[2].to_string(),
+            user_id: None,
+            avatar_name: caps[3].to_string(),
+        },
+    },
+    LogDefinition {
+        pattern_part: r"\[VRC Camera\] Took screenshot to: (.+)",
+        factory: |caps| VrcLogEvent::Screenshot {
+            path: caps[2].to_string(),
+        },
+    },
+    LogDefinition {
+        pattern_part: r"\[String Download\] Attempting to load String from URL '(.+)'",
+        factory: |caps| VrcLogEvent::RemoteContentLoad {
+            kind: RemoteContentKind::String,
+            url: caps[2].to_string(),
+        },
+    },
+    LogDefinition {
+        pattern_part: r"\[Image Download\] Attempting to load image from URL '(.+)'",
+        factory: |caps| VrcLogEvent::RemoteContentLoad {
+            kind: RemoteContentKind::Image,
+            url: caps[2].to_string(),
+        },
+    },
+    // 動画プレイヤー (Unity Video Player / AVPro 共通の URL 解決)
+    // 1回の再生で複数行出力されるため、SessionTracker が1つのイベントにまとめる
+    LogDefinition {
+        pattern_part: r"\[Video Playback\] URL '(.+?)' resolved to '(.+)'",
+        factory: |caps| VrcLogEvent::VideoPlay {
+            url: caps[2].to_string(),
+            resolved_url: Some(caps[3].to_string()),
+            requested_by: None,
+        },
+    },
+    // AVPro が直接開いたURL (ライブ配信など解決を通らないものも含む)
+    LogDefinition {
+        pattern_part: r"\[AVProVideo\] Opening (\S+)",
+        factory: |caps| VrcLogEvent::VideoPlay {
+            url: caps[2].to_string(),
+            resolved_url: None,
+            requested_by: None,
+        },
+    },
+    // USharpVideo はリクエストしたプレイヤー名も出力する
+    LogDefinition {
+        pattern_part: r"USharpVideo\S*\] Started video load for URL: (.+?), requested by (.+)",
+        factory: |caps| VrcLogEvent::VideoPlay {
+            url: caps[2].to_string(),
+            resolved_url: None,
+            requested_by: Some(caps[3].to_string()),
+        },
+    },
 ];
 
 struct CompiledMatcher {
@@ -148,23 +331,250 @@ pub fn parse_log_line(line: &str) -> Option<Payload> {
     }
     None
 }
-/// 1行解析してイベントを送信する内部関数
-fn process_log_line(line: &str, app: &AppHandle, db: &LogDatabase) {
-    if let Some(payload) = parse_log_line(line) {
-        // to frontend
-        if let Err(e) = Payload::emit(&payload, app) {
-            eprintln!("Failed to emit log event: {}", e);
+
+/// 1つのログファイル内のセッション状態 (インスタンスにいるプレイヤーの名前 → user_id)
+/// アバター変更の行には名前しか出力されないため、これを使って user_id を補完する
+#[derive(Default)]
+pub(crate) struct SessionTracker {
+    /// ログインユーザーの (名前, user_id)
+    me: Option<(String, String)>,
+    players: HashMap<String, String>,
+    /// 動画をリクエストしたプレイヤー (URL, 名前)。続く URL 解決の行に付ける
+    video_request: Option<(String, String)>,
+    /// 直前に解決された動画の URL。続く AVPro の Opening は同じ再生なので出力しない
+    resolved_video: Option<String>,
+}
+
+impl SessionTracker {
+    /// イベントを状態に反映し、user_id が不明なイベントを補完する
+    /// 1回の動画再生は URL 解決 (なければ AVPro の Opening) の1イベントにまとめ、それ以外の行は false を返す
+    pub(crate) fn observe(&mut self, event: &mut VrcLogEvent) -> bool {
+        match event {
+            VrcLogEvent::Login { username, user_id } => {
+                self.me = Some((username.clone(), user_id.clone()));
+            }
+            VrcLogEvent::PlayerJoin {
+                player_name,
+                user_id,
+            } => {
+                self.players.insert(player_name.clone(), user_id.clone());
+            }
+            VrcLogEvent::PlayerLeft { player_name, .. } => {
+                self.players.remove(player_name);
+            }
+            VrcLogEvent::InstanceJoin { .. } | VrcLogEvent::SelfLeft => {
+                self.players.clear();
+                self.video_request = None;
+                self.resolved_video = None;
+            }
+            VrcLogEvent::AvatarChange {
+                player_name,
+                user_id: user_id @ None,
+                ..
+            } => *user_id = self.user_id_of(player_name),
+            VrcLogEvent::VideoPlay {
+                url,
+                resolved_url,
+                requested_by,
+            } => {
+                if let Some(name) = requested_by.take() {
+                    // リクエストの行 (USharpVideo) は再生が始まる前に出力される
+                    self.video_request = Some((url.clone(), name));
+                    return false;
+                }
+                match resolved_url {
+                    Some(resolved) => self.resolved_video = Some(resolved.clone()),
+                    None if self.resolved_video.as_deref() == Some(url.as_str()) => {
+                        self.resolved_video = None;
+                        return false;
+                    }
+                    None => {}
+                }
+                if self
+                    .video_request
+                    .as_ref()
+                    .is_some_and(|(requested, _)| requested == url)
+                {
+                    *requested_by = self.video_request.take().map(|(_, name)| name);
+                }
+            }
+            _ => {}
         }
-        // to DataBase
-        if let Err(e) = db.insert_log(&payload) {
-            eprintln!("Failed to save log to DB: {}", e);
+        true
+    }
+
+    fn user_id_of(&self, player_name: &str) -> Option<String> {
+        self.players.get(player_name).cloned().or_else(|| {
+            self.me
+                .as_ref()
+                .filter(|(name, _)| name == player_name)
+                .map(|(_, id)| id.clone())
+        })
+    }
+}
+
+/// 解析済みのイベントを保存・送信する内部関数
+fn process_log_line(
+    payload: Payload,
+    source: LogSource,
+    app: &AppHandle,
+    db: &LogDatabase,
+    hub: &EventHub,
+    monitor: &WatcherMonitor,
+) {
+    metrics().event_matched(payload.event.kind());
+    let forwarder = app.try_state::<Forwarder>();
+    let mode = forwarder.as_ref().map_or(ForwardMode::Off, |f| f.mode());
+    // to DataBase
+    if mode != ForwardMode::ForwardOnly {
+        match db.insert_log(&payload, Some(&source)) {
+            // to LAN clients (WebSocket / SSE)
+            Ok(InsertOutcome::Inserted(id)) => {
+                if let VrcLogEvent::Screenshot { .. } = payload.event {
+                    screenshot::embed_in_background(db, id);
+                }
+                hub.publish(LogRecord {
+                    id,
+                    payload: payload.clone(),
+                })
+            }
+            // 取り込み済みのイベントは frontend へも転送先へも送らない
+            Ok(InsertOutcome::Skipped) => return,
+            Err(e) => monitor.error(format!("Failed to save log to DB: {}", e)),
         }
-        return;
+    }
+    // to remote VRCP
+    if let Some(forwarder) = forwarder {
+        forwarder.forward([(&payload, &source)]);
+    }
+    // to frontend
+    if let Err(e) = Payload::emit(&payload, app) {
+        eprintln!("Failed to emit log event: {}", e);
     }
 }
 
 // ================================================================
-// Section B: File Watcher Logic
+// Section B: Watcher Status
+// (監視の状態。/health や /info で LAN クライアントに公開する)
+// ================================================================
+
+/// ログ監視の状態
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+pub struct WatcherStatus {
+    /// 監視ループが動いているか
+    pub running: bool,
+    /// 起動時の過去ログ取り込み中か
+    pub backfilling: bool,
+    /// 監視中のログファイル名
+    pub current_file: Option<String>,
+    /// 最後に行を読んだ時刻 (RFC 3339)
+    pub last_line_at: Option<String>,
+    /// 起動してから読んだ行数
+    pub lines_read: u64,
+    /// 起動してから発生したエラーの数
+    pub error_count: u64,
+    /// 最後に発生したエラー
+    pub last_error: Option<String>,
+}
+
+/// 監視ループがこの時間ハートビートを記録しなければ止まっているとみなす
+/// (大きなログファイルを開き直す間も止まっているとみなされないよう、ポーリング間隔の数倍)
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// watcher と HTTPサーバーで共有する監視状態
+#[derive(Clone, Default)]
+pub struct WatcherMonitor {
+    status: Arc<Mutex<WatcherStatus>>,
+    /// 監視ループが最後に回った時刻
+    heartbeat: Arc<Mutex<Option<Instant>>>,
+}
+
+/// 監視タスクが動いている間だけ `running` を立てておくガード
+/// タスクが終了・パニックして破棄されると `running` を下ろす
+pub struct RunningGuard {
+    monitor: WatcherMonitor,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.monitor.update(|s| s.running = false);
+    }
+}
+
+impl WatcherMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 現在の状態のコピーを取得する
+    pub fn snapshot(&self) -> WatcherStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut WatcherStatus)) {
+        f(&mut self.status.lock().unwrap());
+    }
+
+    fn set_backfilling(&self, backfilling: bool) {
+        self.update(|s| s.backfilling = backfilling);
+    }
+
+    /// 監視を開始したことを記録する (返したガードが破棄されるまで running)
+    pub(crate) fn start(&self) -> RunningGuard {
+        self.heartbeat();
+        self.update(|s| s.running = true);
+        RunningGuard {
+            monitor: self.clone(),
+        }
+    }
+
+    /// 監視ループが止まっていないことを記録する
+    fn heartbeat(&self) {
+        *self.heartbeat.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// 監視が正常に動いているか (/health 用)
+    /// 過去ログの取り込み中か、監視ループが直近にハートビートを記録していれば正常
+    pub fn is_healthy(&self) -> bool {
+        self.is_healthy_at(Instant::now())
+    }
+
+    fn is_healthy_at(&self, now: Instant) -> bool {
+        let status = self.snapshot();
+        if status.backfilling {
+            return true;
+        }
+        let heartbeat = *self.heartbeat.lock().unwrap();
+        status.running
+            && heartbeat.is_some_and(|beat| now.saturating_duration_since(beat) < HEARTBEAT_TIMEOUT)
+    }
+
+    fn set_file(&self, path: Option<&Path>) {
+        let name = path
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string());
+        self.update(|s| s.current_file = name);
+    }
+
+    fn line_read(&self) {
+        let now = chrono::Local::now().to_rfc3339();
+        self.update(|s| {
+            s.lines_read += 1;
+            s.last_line_at = Some(now);
+        });
+    }
+
+    fn error(&self, message: String) {
+        eprintln!("{}", message);
+        self.update(|s| {
+            s.error_count += 1;
+            s.last_error = Some(message);
+        });
+    }
+}
+
+// ================================================================
+// Section C: File Watcher Logic
 // (ファイル探索、ループ処理、ローテーション検知など、ファイル操作に関する処理)
 // ================================================================
 
@@ -179,10 +589,11 @@ fn get_vrc_log_dir() -> Option<PathBuf> {
     })
 }
 
-/// 最新のログファイルパスを取得
-fn get_latest_log_path() -> Option<PathBuf> {
-    let log_dir = get_vrc_log_dir()?;
-    let entries = fs::read_dir(log_dir).ok()?;
+/// ログディレクトリ内の全ログファイルを古い順に取得
+pub(crate) fn list_log_files() -> Vec<PathBuf> {
+    let Some(entries) = get_vrc_log_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
 
     let mut logs: Vec<PathBuf> = entries
         .filter_map(|entry| entry.ok())
@@ -197,23 +608,175 @@ fn get_latest_log_path() -> Option<PathBuf> {
         .collect();
 
     logs.sort_by_key(|path| path.metadata().and_then(|m| m.modified()).ok());
-    logs.last().cloned()
+    logs
+}
+
+/// 最新のログファイルパスを取得
+fn get_latest_log_path() -> Option<PathBuf> {
+    list_log_files().pop()
+}
+
+/// チェックポイントを保存する間隔 (行数)
+const CHECKPOINT_INTERVAL_LINES: u64 = 1000;
+
+/// ファイルの同一性を判定するための識別子を取得する
+/// VRChatのログは先頭行に起動時刻が入るため、先頭行をそのまま識別子として使う
+fn read_file_id(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    reader.read_until(b'\n', &mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).trim_end().to_string())
+}
+
+/// 読み込み位置を管理しながらログファイルを1行ずつ読むリーダー
+pub(crate) struct TailedLogFile {
+    path: PathBuf,
+    file_id: String,
+    reader: BufReader<File>,
+    /// 処理済み(改行まで読み終えた)バイトオフセット
+    offset: u64,
+    /// DBに保存済みのオフセット
+    saved_offset: u64,
+    pub(crate) lines_since_save: u64,
+    /// 書き込み途中の行のバッファ
+    buf: Vec<u8>,
+    session: SessionTracker,
+}
+
+impl TailedLogFile {
+    /// ファイルを開き、チェックポイントがあればその位置から再開する
+    /// チェックポイントがない (初めて見る) ファイルは先頭から読む
+    pub(crate) fn open(path: &Path, db: &LogDatabase) -> io::Result<Self> {
+        let file_id = read_file_id(path)?;
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let path_str = path.to_string_lossy().to_string();
+
+        let offset = match db.get_checkpoint(&path_str) {
+            // 同じファイルで、かつ切り詰められていなければ前回の続きから
+            Ok(Some(cp)) if cp.file_id == file_id && cp.offset <= len => cp.offset,
+            Ok(Some(_)) => {
+                println!("Log file changed since last checkpoint. Reading from start.");
+                0
+            }
+            Ok(None) => 0,
+            Err(e) => {
+                eprintln!("Failed to load checkpoint: {}", e);
+                0
+            }
+        };
+        // 途中から再開する場合は、読み飛ばす部分からセッション状態だけを復元する
+        // (読み終えたファイルにも VRChat が追記を続けるので、末尾まで読んでいても復元する)
+        let session = if offset > 0 {
+            replay_session(&mut file, offset)?
+        } else {
+            SessionTracker::default()
+        };
+        file.seek(SeekFrom::Start(offset))?;
+
+        Ok(TailedLogFile {
+            path: path.to_path_buf(),
+            file_id,
+            reader: BufReader::new(file),
+            offset,
+            saved_offset: offset,
+            lines_since_save: 0,
+            buf: Vec::new(),
+            session,
+        })
+    }
+
+    /// 1行を解析し、このファイルのセッション状態で補完したイベントを返す
+    pub(crate) fn parse(&mut self, line: &str) -> Option<Payload> {
+        let mut payload = parse_log_line(line)?;
+        self.session.observe(&mut payload.event).then_some(payload)
+    }
+
+    /// 改行まで揃った1行を、その開始オフセットと共に読む。読めるものがなければ None
+    pub(crate) fn next_line(&mut self) -> io::Result<Option<(u64, String)>> {
+        let read = self.reader.read_until(b'\n', &mut self.buf)?;
+        if read == 0 || !self.buf.ends_with(b"\n") {
+            // EOF、または書き込み途中の行 (続きは次回の読み込みで連結される)
+            return Ok(None);
+        }
+        let line = String::from_utf8_lossy(&self.buf).to_string();
+        if self.offset == 0 && self.file_id.is_empty() {
+            // 空の状態で開いたファイルは、最初の行が書かれた時点で識別子を確定する
+            self.file_id = line.trim_end().to_string();
+        }
+        let line_offset = self.offset;
+        self.offset += self.buf.len() as u64;
+        self.lines_since_save += 1;
+        self.buf.clear();
+        Ok(Some((line_offset, line)))
+    }
+
+    /// 指定オフセットの行の取り込み元情報を作る
+    pub(crate) fn source_at(&self, offset: u64) -> LogSource {
+        LogSource {
+            file: self
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            offset,
+        }
+    }
+
+    /// 読み込み位置をDBへ保存する
+    fn save(&self, db: &LogDatabase) {
+        let checkpoint = LogCheckpoint {
+            path: self.path.to_string_lossy().to_string(),
+            file_id: self.file_id.clone(),
+            offset: self.offset,
+        };
+        if let Err(e) = db.save_checkpoint(&checkpoint) {
+            eprintln!("Failed to save log checkpoint: {}", e);
+        }
+    }
+
+    /// 未保存の進捗があれば保存する
+    pub(crate) fn save_if_dirty(&mut self, db: &LogDatabase) {
+        if self.offset != self.saved_offset {
+            self.save(db);
+            self.saved_offset = self.offset;
+            self.lines_since_save = 0;
+        }
+    }
+}
+
+/// ファイルの先頭から `until` までを解析してセッション状態を作る (保存はしない)
+fn replay_session(file: &mut File, until: u64) -> io::Result<SessionTracker> {
+    let mut session = SessionTracker::default();
+    let mut reader = BufReader::new(Read::by_ref(file).take(until));
+    let mut buf = Vec::new();
+    while reader.read_until(b'\n', &mut buf)? > 0 {
+        if let Some(mut payload) = parse_log_line(&String::from_utf8_lossy(&buf)) {
+            session.observe(&mut payload.event);
+        }
+        buf.clear();
+    }
+    Ok(session)
 }
 
 /// ログ監視タスクのメインループ（非同期）
-async fn watch_loop(app: AppHandle, db: LogDatabase) {
+async fn watch_loop(app: AppHandle, db: LogDatabase, hub: EventHub, monitor: WatcherMonitor) {
     let mut rotation_check_interval = tokio::time::interval(Duration::from_secs(5));
     let mut current_log_path = get_latest_log_path();
+    monitor.set_file(current_log_path.as_deref());
 
     // 初回オープン処理
     let mut reader = match &current_log_path {
         Some(path) => {
             println!("Start watching log file: {:?}", path);
-            File::open(path).ok().map(|mut f| {
-                // 初回は過去ログを読まないよう末尾にシーク
-                let _ = f.seek(SeekFrom::End(0));
-                BufReader::new(f)
-            })
+            // 起動前の分は取り込み済みなので、チェックポイントの続きから読む
+            match TailedLogFile::open(path, &db) {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    monitor.error(format!("Failed to open log file: {}", e));
+                    None
+                }
+            }
         }
         None => {
             println!("No VRChat log file found yet. Waiting for creation...");
@@ -221,21 +784,29 @@ async fn watch_loop(app: AppHandle, db: LogDatabase) {
         }
     };
 
-    let mut line = String::new();
-
     loop {
+        monitor.heartbeat();
         let mut read_success = false;
 
         // 1. 現在のリーダーから行を読み込む
         if let Some(r) = &mut reader {
-            match r.read_line(&mut line) {
-                Ok(0) => { /* EOF */ }
-                Ok(_) => {
-                    process_log_line(&line, &app, &db);
-                    line.clear();
+            match r.next_line() {
+                Ok(Some((offset, line))) => {
+                    metrics().line_read();
+                    if let Some(payload) = r.parse(&line) {
+                        process_log_line(payload, r.source_at(offset), &app, &db, &hub, &monitor);
+                    }
+                    monitor.line_read();
                     read_success = true;
+                    if r.lines_since_save >= CHECKPOINT_INTERVAL_LINES {
+                        r.save_if_dirty(&db);
+                    }
+                }
+                Ok(None) => {
+                    // EOF: ここまでの進捗を保存
+                    r.save_if_dirty(&db);
                 }
-                Err(e) => eprintln!("Error reading log: {}", e),
+                Err(e) => monitor.error(format!("Error reading log: {}", e)),
             }
         }
 
@@ -256,17 +827,19 @@ async fn watch_loop(app: AppHandle, db: LogDatabase) {
                 // パスが変わっていたら（＝新ファイル生成 or 初めて見つかった）
                 if latest != current_log_path {
                     println!("Log rotation detected! Switching to: {:?}", latest);
+                    metrics().rotation_detected();
                     current_log_path = latest.clone();
+                    monitor.set_file(current_log_path.as_deref());
 
                     if let Some(path) = latest {
-                        match File::open(&path) {
+                        // 新しいファイルは「先頭」から読む（Start Upイベントなどを逃さないため）
+                        match TailedLogFile::open(&path, &db) {
                             Ok(f) => {
-                                // 新しいファイルは「先頭」から読む（Start Upイベントなどを逃さないため）
-                                reader = Some(BufReader::new(f));
+                                reader = Some(f);
                                 println!("Switched to new log file successfully.");
                             }
                             Err(e) => {
-                                eprintln!("Failed to open new log file: {}", e);
+                                monitor.error(format!("Failed to open new log file: {}", e));
                                 reader = None;
                             }
                         }
@@ -282,8 +855,294 @@ async fn watch_loop(app: AppHandle, db: LogDatabase) {
 // ================================================================
 
 /// 監視タスクをバックグラウンドで開始する
-pub fn spawn_log_watcher(app: AppHandle, db: LogDatabase) {
+pub fn spawn_log_watcher(app: AppHandle, db: LogDatabase, hub: EventHub, monitor: WatcherMonitor) {
     tauri::async_runtime::spawn(async move {
-        watch_loop(app, db).await;
+        // 既存のログ (初回は全て、以降は停止中に書かれた分) を取り込んでから監視を始める
+        let (backfill_app, backfill_db) = (app.clone(), db.clone());
+        monitor.set_backfilling(true);
+        let _ = tauri::async_runtime::spawn_blocking(move || {
+            backfill::run_backfill(&backfill_app, &backfill_db);
+        })
+        .await;
+        monitor.set_backfilling(false);
+        // watch_loop が終了・パニックしたら running を下ろす
+        let _running = monitor.start();
+        watch_loop(app, db, hub, monitor.clone()).await;
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    const LINE_1: &str = "2024.01.01 10:00:00 Log        -  [Behaviour] Entering Room: Home\n";
+    const LINE_2: &str =
+        "2024.01.01 10:00:01 Log        -  [Behaviour] OnPlayerJoined Alice (usr_a)\n";
+    const LINE_3: &str =
+        "2024.01.01 10:00:02 Log        -  [Behaviour] OnPlayerLeft Alice (usr_a)\n";
+
+    fn setup() -> (tempfile::TempDir, LogDatabase, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LogDatabase::new(dir.path().join("data")).unwrap();
+        let path = dir.path().join("output_log_2024-01-01_10-00-00.txt");
+        (dir, db, path)
+    }
+
+    fn append(path: &Path, text: &str) {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    /// ファイル末尾まで読んだ行
+    fn read_all(file: &mut TailedLogFile) -> Vec<(u64, String)> {
+        let mut lines = Vec::new();
+        while let Some(line) = file.next_line().unwrap() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    #[test]
+    fn half_written_line_is_joined_across_reads() {
+        let (_dir, db, path) = setup();
+        append(&path, LINE_1);
+        append(&path, &LINE_2[..20]);
+
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        assert_eq!(read_all(&mut file), vec![(0, LINE_1.to_string())]);
+
+        append(&path, &LINE_2[20..]);
+        assert_eq!(
+            read_all(&mut file),
+            vec![(LINE_1.len() as u64, LINE_2.to_string())]
+        );
+    }
+
+    #[test]
+    fn reopening_resumes_at_saved_offset() {
+        let (_dir, db, path) = setup();
+        append(&path, LINE_1);
+        append(&path, LINE_2);
+
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        assert_eq!(read_all(&mut file).len(), 2);
+        file.save_if_dirty(&db);
+        drop(file);
+
+        append(&path, LINE_3);
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        assert_eq!(
+            read_all(&mut file),
+            vec![((LINE_1.len() + LINE_2.len()) as u64, LINE_3.to_string())]
+        );
+    }
+
+    #[test]
+    fn truncated_file_is_read_from_start() {
+        let (_dir, db, path) = setup();
+        append(&path, LINE_1);
+        append(&path, LINE_2);
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        read_all(&mut file);
+        file.save_if_dirty(&db);
+        drop(file);
+
+        // 先頭行は同じだが、保存したオフセットより短くなった
+        fs::write(&path, LINE_1).unwrap();
+        let file = TailedLogFile::open(&path, &db).unwrap();
+        assert_eq!(file.offset, 0);
+    }
+
+    #[test]
+    fn changed_first_line_is_read_from_start() {
+        let (_dir, db, path) = setup();
+        append(&path, LINE_1);
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        read_all(&mut file);
+        file.save_if_dirty(&db);
+        drop(file);
+
+        // 同じパスに別のログが書かれた (保存したオフセットより長い)
+        fs::write(&path, format!("{}{}{}", LINE_2, LINE_3, LINE_1)).unwrap();
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        assert_eq!(file.offset, 0);
+        assert_eq!(read_all(&mut file)[0], (0, LINE_2.to_string()));
+    }
+
+    /// 行を順に SessionTracker に通し、出力されるイベントを返す
+    fn observe_all(session: &mut SessionTracker, lines: &[&str]) -> Vec<VrcLogEvent> {
+        lines
+            .iter()
+            .filter_map(|line| parse_log_line(line))
+            .map(|payload| payload.event)
+            .filter_map(|mut event| session.observe(&mut event).then_some(event))
+            .collect()
+    }
+
+    fn video(url: &str, resolved_url: Option<&str>, requested_by: Option<&str>) -> VrcLogEvent {
+        VrcLogEvent::VideoPlay {
+            url: url.to_string(),
+            resolved_url: resolved_url.map(str::to_string),
+            requested_by: requested_by.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn video_playback_becomes_one_event() {
+        let mut session = SessionTracker::default();
+        let events = observe_all(
+            &mut session,
+            &[
+                "2024.01.01 10:00:00 Log        -  [USharpVideo] Started video load for URL: https://youtu.be/x, requested by Alice",
+                "2024.01.01 10:00:00 Log        -  [Video Playback] Attempting to resolve URL 'https://youtu.be/x'",
+                "2024.01.01 10:00:01 Log        -  [Video Playback] URL 'https://youtu.be/x' resolved to 'https://cdn/x.mp4'",
+                "2024.01.01 10:00:01 Log        -  [AVProVideo] Opening https://cdn/x.mp4",
+                // 解決を通らないライブ配信は Opening だけが出力される
+                "2024.01.01 10:05:00 Log        -  [AVProVideo] Opening rtspt://live/stream",
+            ],
+        );
+        assert_eq!(
+            events,
+            vec![
+                video(
+                    "https://youtu.be/x",
+                    Some("https://cdn/x.mp4"),
+                    Some("Alice")
+                ),
+                video("rtspt://live/stream", None, None),
+            ]
+        );
+    }
+
+    fn avatar(player_name: &str, user_id: Option<&str>) -> VrcLogEvent {
+        VrcLogEvent::AvatarChange {
+            player_name: player_name.to_string(),
+            user_id: user_id.map(str::to_string),
+            avatar_name: "Robot".to_string(),
+        }
+    }
+
+    const AVATAR_ALICE: &str =
+        "2024.01.01 10:00:03 Log        -  [Behaviour] Switching Alice to avatar Robot";
+
+    #[test]
+    fn avatar_change_gets_user_id_of_player_in_instance() {
+        let mut session = SessionTracker::default();
+        let events = observe_all(
+            &mut session,
+            &[
+                "2024.01.01 10:00:00 Log        -  [Behaviour] Joining wrld_a:1",
+                "2024.01.01 10:00:01 Log        -  [Behaviour] OnPlayerJoined Alice (usr_a)",
+                AVATAR_ALICE,
+            ],
+        );
+        assert_eq!(events.last(), Some(&avatar("Alice", Some("usr_a"))));
+    }
+
+    #[test]
+    fn avatar_change_uses_id_under_current_name() {
+        let mut session = SessionTracker::default();
+        // 表示名を変えて入り直すと、同じ user_id でも新しい名前で記録される
+        let events = observe_all(
+            &mut session,
+            &[
+                "2024.01.01 10:00:01 Log        -  [Behaviour] OnPlayerJoined Bob (usr_a)",
+                "2024.01.01 10:00:02 Log        -  [Behaviour] OnPlayerLeft Bob (usr_a)",
+                "2024.01.01 10:00:03 Log        -  [Behaviour] OnPlayerJoined Alice (usr_a)",
+                AVATAR_ALICE,
+                "2024.01.01 10:00:04 Log        -  [Behaviour] Switching Bob to avatar Robot",
+            ],
+        );
+        assert_eq!(
+            events[3..],
+            [avatar("Alice", Some("usr_a")), avatar("Bob", None)]
+        );
+    }
+
+    #[test]
+    fn players_are_forgotten_after_leaving_instance() {
+        let mut session = SessionTracker::default();
+        let events = observe_all(
+            &mut session,
+            &[
+                "2024.01.01 10:00:00 Log        -  [Behaviour] Joining wrld_a:1",
+                "2024.01.01 10:00:01 Log        -  [Behaviour] OnPlayerJoined Alice (usr_a)",
+                "2024.01.01 10:00:02 Log        -  [Behaviour] OnLeftRoom",
+                "2024.01.01 10:00:02 Log        -  [Behaviour] Joining wrld_b:2",
+                AVATAR_ALICE,
+                // 入り直せば再び補完できる
+                "2024.01.01 10:00:04 Log        -  [Behaviour] OnPlayerJoined Alice (usr_a)",
+                AVATAR_ALICE,
+            ],
+        );
+        assert_eq!(events[4], avatar("Alice", None));
+        assert_eq!(events[6], avatar("Alice", Some("usr_a")));
+    }
+
+    #[test]
+    fn local_user_is_known_in_every_instance() {
+        let mut session = SessionTracker::default();
+        let events = observe_all(
+            &mut session,
+            &[
+                "2024.01.01 09:59:00 Log        -  User Authenticated: Alice (usr_a)",
+                "2024.01.01 10:00:00 Log        -  [Behaviour] Joining wrld_a:1",
+                AVATAR_ALICE,
+            ],
+        );
+        assert_eq!(events.last(), Some(&avatar("Alice", Some("usr_a"))));
+    }
+
+    #[test]
+    fn session_is_restored_when_reopening_fully_read_file() {
+        let (_dir, db, path) = setup();
+        append(&path, LINE_1);
+        append(&path, LINE_2);
+
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        read_all(&mut file);
+        file.save_if_dirty(&db);
+        drop(file);
+
+        // 末尾まで読んだ状態で開き直し、その後に追記された行でも
+        // それまでに参加したプレイヤーを補完できる
+        let mut file = TailedLogFile::open(&path, &db).unwrap();
+        append(&path, &format!("{}\n", AVATAR_ALICE));
+        let (_, line) = read_all(&mut file).remove(0);
+        assert_eq!(
+            file.parse(&line).map(|payload| payload.event),
+            Some(avatar("Alice", Some("usr_a")))
+        );
+    }
+
+    #[test]
+    fn watcher_is_unhealthy_after_task_ends() {
+        let monitor = WatcherMonitor::new();
+        assert!(!monitor.is_healthy());
+
+        let running = monitor.start();
+        assert!(monitor.is_healthy());
+        drop(running);
+        assert!(!monitor.snapshot().running);
+        assert!(!monitor.is_healthy());
+    }
+
+    #[test]
+    fn watcher_is_unhealthy_when_heartbeat_stops() {
+        let monitor = WatcherMonitor::new();
+        let _running = monitor.start();
+        let now = Instant::now();
+        assert!(monitor.is_healthy_at(now));
+        assert!(!monitor.is_healthy_at(now + HEARTBEAT_TIMEOUT));
+
+        monitor.heartbeat();
+        assert!(monitor.is_healthy_at(Instant::now()));
+    }
+}
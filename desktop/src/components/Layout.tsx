@@ -1,8 +1,10 @@
 import { Outlet, Link, useLocation } from "react-router-dom";
 import { Activity, Settings, BarChart3 } from "lucide-react"; // アイコン
+import { useLogContext } from "../context/LogContext";
 
 export default function Layout() {
   const location = useLocation();
+  const { backfill } = useLogContext();
 
   const navItems = [
     { path: "/", label: "Monitor", icon: Activity },
@@ -38,7 +40,23 @@ export default function Layout() {
           })}
         </nav>
 
-        <div className="mt-auto pt-4 border-t border-slate-800">
+        {/* 過去ログ取り込みの進捗 */}
+        {backfill && !backfill.finished && (
+          <div className="mt-auto mb-4 px-2">
+            <p className="text-xs text-slate-400 mb-1">
+              Importing logs... ({backfill.files_done}/{backfill.files_total})
+            </p>
+            <div className="w-full h-1.5 bg-slate-800 rounded-full overflow-hidden">
+              <div
+                className="h-full bg-blue-500 transition-all"
+                style={{ width: `${backfill.files_total ? (backfill.files_done / backfill.files_total) * 100 : 0}%` }}
+              />
+            </div>
+            <p className="text-[10px] text-slate-500 mt-1 truncate">{backfill.events_imported} events</p>
+          </div>
+        )}
+
+        <div className={`${backfill && !backfill.finished ? "" : "mt-auto "}pt-4 border-t border-slate-800`}>
           <p className="text-xs text-slate-600 text-center">v0.1.0 Alpha</p>
         </div>
       </aside>
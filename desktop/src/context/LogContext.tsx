@@ -1,9 +1,10 @@
 import { createContext, useContext, useEffect, useState, ReactNode } from "react";
-import { events, commands, type Payload } from "../generated/bindings";
+import { events, commands, type Payload, type BackfillProgress } from "../generated/bindings";
 
 interface LogContextType {
   logs: Payload[];
   serverUrl: string;
+  backfill: BackfillProgress | null;
   clearLogs: () => void;
 }
 
@@ -12,6 +13,7 @@ const LogContext = createContext<LogContextType | undefined>(undefined);
 export function LogProvider({ children }: { children: ReactNode }) {
   const [logs, setLogs] = useState<Payload[]>([]);
   const [serverUrl, setServerUrl] = useState<string>("");
+  const [backfill, setBackfill] = useState<BackfillProgress | null>(null);
 
   useEffect(() => {
     // 1. サーバーURL取得
@@ -32,8 +34,14 @@ export function LogProvider({ children }: { children: ReactNode }) {
       setLogs((prev) => [...prev, event.payload]);
     });
 
+    // 3. 過去ログ取り込みの進捗
+    const unlistenBackfillPromise = events.backfillProgress.listen((event) => {
+      setBackfill(event.payload);
+    });
+
     return () => {
       unlistenPromise.then((unlisten) => unlisten());
+      unlistenBackfillPromise.then((unlisten) => unlisten());
       clearInterval(unsubscribe);
     };
   }, []);
@@ -41,7 +49,7 @@ export function LogProvider({ children }: { children: ReactNode }) {
   const clearLogs = () => setLogs([]);
 
   return (
-    <LogContext.Provider value={{ logs, serverUrl, clearLogs }}>
+    <LogContext.Provider value={{ logs, serverUrl, backfill, clearLogs }}>
       {children}
     </LogContext.Provider>
   );
@@ -30,9 +30,186 @@ async getServerUrl() : Promise<Result<string, string>> {
     else return { status: "error", error: e  as any };
 }
 },
-async getLogs(start: string | null, end: string | null) : Promise<Result<Payload[], string>> {
+async getTlsEnabled() : Promise<Result<boolean, string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("get_logs", { start, end }) };
+    return { status: "ok", data: await TAURI_INVOKE("get_tls_enabled") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async setTlsEnabled(enabled: boolean) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_tls_enabled", { enabled }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * SHA-256 fingerprint of the server certificate, for pinning on the mobile side
+ */
+async getCertificateFingerprint() : Promise<Result<string | null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_certificate_fingerprint") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getBindMode() : Promise<Result<BindMode, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_bind_mode") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async setBindMode(mode: BindMode) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_bind_mode", { mode }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async listNetworkAddresses() : Promise<Result<NetworkAddress[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_network_addresses") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Lan モードで接続用URLに使うアドレスを取得する (自動なら null)
+ */
+async getAdvertisedAddress() : Promise<Result<string | null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_advertised_address") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Lan モードで接続用URLに使うアドレスを設定する (null なら自動)
+ */
+async setAdvertisedAddress(address: string | null) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_advertised_address", { address }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getForwardConfig() : Promise<Result<ForwardConfig, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_forward_config") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async setForwardConfig(config: ForwardConfig) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_forward_config", { config }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getForwardStatus() : Promise<Result<ForwardStatus, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_forward_status") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getPairingInfo() : Promise<Result<PairingInfo, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_pairing_info") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async rotatePairingToken() : Promise<Result<PairingInfo, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("rotate_pairing_token") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async revokePairingToken() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("revoke_pairing_token") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getLogs(start: string | null, end: string | null, filter: LogFilter | null, limit: number | null, cursor: string | null) : Promise<Result<LogPage, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_logs", { start, end, filter, limit, cursor }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getMyAvatars(start: string | null, end: string | null) : Promise<Result<AvatarUsage[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_my_avatars", { start, end }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getPlayerAvatars(userId: string, start: string | null, end: string | null) : Promise<Result<AvatarEncounter[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_player_avatars", { userId, start, end }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getWorldHosts(start: string | null, end: string | null) : Promise<Result<WorldHostUsage[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_world_hosts", { start, end }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getScreenshots(start: string | null, end: string | null, instanceId: string | null, userId: string | null) : Promise<Result<ScreenshotInfo[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_screenshots", { start, end, instanceId, userId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getEmbedScreenshotMetadata() : Promise<Result<boolean, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_embed_screenshot_metadata") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async setEmbedScreenshotMetadata(enabled: boolean) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_embed_screenshot_metadata", { enabled }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async importScreenshots(dir: string) : Promise<Result<number, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("import_screenshots", { dir }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
@@ -53,6 +230,17 @@ async exportLogs(filePath: string) : Promise<Result<number, string>> {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
+},
+/**
+ * 視聴した動画の一覧を JSON ファイルに書き出す
+ */
+async exportWatchList(filePath: string) : Promise<Result<number, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_watch_list", { filePath }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
 }
 }
 
@@ -61,10 +249,12 @@ async exportLogs(filePath: string) : Promise<Result<number, string>> {
 
 export const events = __makeEvents__<{
 payload: Payload,
-vrcLogEvent: VrcLogEvent
+vrcLogEvent: VrcLogEvent,
+backfillProgress: BackfillProgress
 }>({
 payload: "payload",
-vrcLogEvent: "vrc-log-event"
+vrcLogEvent: "vrc-log-event",
+backfillProgress: "backfill-progress"
 })
 
 /** user-defined constants **/
@@ -73,8 +263,220 @@ vrcLogEvent: "vrc-log-event"
 
 /** user-defined types **/
 
+/**
+ * プレイヤーと会ったとき (PlayerJoin) に着用していたアバター (get_player_avatars)
+ */
+export type AvatarEncounter = { 
+/**
+ * PlayerJoin の時刻
+ */
+met_at: string; player_name: string; 
+/**
+ * 退出するまでにアバターの読み込みが記録されていなければ null
+ */
+avatar_name: string | null }
+/**
+ * 自分が着用したアバターの集計 (get_my_avatars)
+ */
+export type AvatarUsage = { avatar_name: string; 
+/**
+ * 期間内にこのアバターへ変更した回数
+ */
+times: number; first_worn: string; last_worn: string }
+export type BackfillProgress = { 
+/**
+ * 処理中のファイル名
+ */
+current_file: string | null; files_done: number; files_total: number; 
+/**
+ * 取り込んだイベント数 (累計)
+ */
+events_imported: number; 
+/**
+ * 取り込み済みだったためスキップしたイベント数 (累計)
+ */
+events_skipped: number; finished: boolean }
+/**
+ * HTTPサーバーの待ち受け範囲
+ */
+export type BindMode = 
+/**
+ * このPCからのみ (127.0.0.1 / ::1)
+ */
+{ mode: "Localhost" } | 
+/**
+ * 全てのネットワーク (0.0.0.0 / ::)
+ */
+{ mode: "Lan" } | 
+/**
+ * 指定したアドレスのインターフェースのみ
+ */
+{ mode: "Interface"; address: string }
+/**
+ * 別のPCの VRCP へイベントを転送する設定
+ */
+export type ForwardConfig = { mode: ForwardMode; 
+/**
+ * 転送先のURL (例: https://192.168.1.5:8727)
+ */
+url: string; 
+/**
+ * 転送先のペアリングトークン
+ */
+token: string; 
+/**
+ * 転送先の証明書の SHA-256 フィンガープリント (https の場合は必須)
+ */
+fingerprint: string | null }
+/**
+ * 転送モード
+ */
+export type ForwardMode = 
+/**
+ * 転送しない
+ */
+"Off" | 
+/**
+ * このPCのDBに保存した上で転送する
+ */
+"Mirror" | 
+/**
+ * このPCのDBには保存せず転送だけする (チェックポイントは保存する)
+ */
+"ForwardOnly"
+/**
+ * 転送の状態
+ */
+export type ForwardStatus = { 
+/**
+ * 送信待ちのイベント数
+ */
+pending_events: number; 
+/**
+ * 最後に送信に成功した時刻 (RFC 3339)
+ */
+last_success_at: string | null; 
+/**
+ * 最後に発生したエラー (成功すると消える)
+ */
+last_error: string | null; 
+/**
+ * 転送先に拒否された、または spool の上限を超えて捨てたイベント数 (起動してからの合計)
+ */
+discarded_events: number }
+/**
+ * get_logs の絞り込み条件 (指定された条件は全て AND で結合される)
+ */
+export type LogFilter = { 
+/**
+ * 取得するイベントの種別 (未指定・空なら全種別)
+ */
+kinds: VrcLogEventKind[] | null; 
+/**
+ * プレイヤー / ログインユーザーの user_id (usr_xxx)
+ */
+user_id: string | null; 
+/**
+ * ワールドID (wrld_xxx)
+ * そのワールドに滞在中のイベント (同じログファイルで直前に参加したインスタンスのワールドで判定する)
+ */
+world_id: string | null; 
+/**
+ * プレイヤー名・ユーザー名・ワールド名の部分一致
+ */
+text: string | null }
+/**
+ * get_logs の1ページ分の結果
+ */
+export type LogPage = { items: Payload[]; 
+/**
+ * 次のページを取得するためのカーソル (最後のページなら null)
+ */
+next_cursor: string | null }
+/**
+ * ネットワークインターフェースのアドレス
+ */
+export type NetworkAddress = { 
+/**
+ * インターフェース名
+ */
+interface: string; address: string; is_ipv6: boolean; is_loopback: boolean }
+/**
+ * モバイルアプリとのペアリング情報
+ */
+export type PairingInfo = { 
+/**
+ * 接続先のURL
+ */
+url: string; 
+/**
+ * Bearer トークン (無効化されている場合は null)
+ */
+token: string | null; 
+/**
+ * サーバー証明書の SHA-256 フィンガープリント (HTTPS の場合)
+ */
+fingerprint: string | null; 
+/**
+ * QRコードに埋め込む文字列 (vrcp://pair?url=...&token=...&fp=...)
+ */
+qr_payload: string | null }
 export type Payload = { event: VrcLogEvent; timestamp: string }
-export type VrcLogEvent = { type: "AppStart" } | { type: "AppStop" } | { type: "Login"; data: { username: string; user_id: string } } | { type: "WorldEnter"; data: { world_name: string } } | { type: "InstanceJoin"; data: { world_id: string; instance_id: string } } | { type: "PlayerJoin"; data: { player_name: string; user_id: string } } | { type: "PlayerLeft"; data: { player_name: string; user_id: string } } | { type: "SelfLeft" }
+/**
+ * 外部から読み込んだデータの種類
+ */
+export type RemoteContentKind = "String" | "Image"
+/**
+ * 撮影した写真と撮影時の状況 (get_screenshots)
+ */
+export type ScreenshotInfo = { timestamp: string; 
+/**
+ * 保存先のフルパス
+ */
+path: string; world_id: string | null; world_name: string | null; instance_id: string | null; players: ScreenshotPlayer[] }
+/**
+ * 写真に写っている (撮影時にインスタンスにいた) プレイヤー
+ */
+export type ScreenshotPlayer = { user_id: string; player_name: string }
+export type VrcLogEvent = { type: "AppStart" } | { type: "AppStop" } | { type: "Login"; data: { username: string; user_id: string } } | { type: "WorldEnter"; data: { world_name: string } } | { type: "InstanceJoin"; data: { world_id: string; instance_id: string } } | { type: "PlayerJoin"; data: { player_name: string; user_id: string } } | { type: "PlayerLeft"; data: { player_name: string; user_id: string } } | { type: "SelfLeft" } | { type: "VideoPlay"; data: { url: string; 
+/**
+ * 実際に再生されるURL (YouTube などを解決した後)
+ */
+resolved_url: string | null; 
+/**
+ * 動画をリクエストしたプレイヤー (プレイヤーが出力している場合のみ)
+ */
+requested_by: string | null } } | { type: "AvatarChange"; data: { player_name: string; 
+/**
+ * ログには名前しか出力されないため、同じインスタンスにいるプレイヤーから補完する
+ */
+user_id: string | null; avatar_name: string } } | { type: "Screenshot"; data: { 
+/**
+ * 保存先のフルパス
+ */
+path: string } } | { type: "RemoteContentLoad"; data: { kind: RemoteContentKind; url: string } }
+/**
+ * VrcLogEvent の種別 (serde の `type` タグと同じ名前)
+ * DBの event_type カラムやフィルタ条件に使う
+ */
+export type VrcLogEventKind = "AppStart" | "AppStop" | "Login" | "WorldEnter" | "InstanceJoin" | "PlayerJoin" | "PlayerLeft" | "SelfLeft" | "VideoPlay" | "AvatarChange" | "Screenshot" | "RemoteContentLoad"
+/**
+ * ワールドが外部データを読み込んだホストの集計 (get_world_hosts)
+ */
+export type WorldHostUsage = { 
+/**
+ * 読み込んだときにいたワールド (インスタンス参加が記録されていなければ null)
+ */
+world_id: string | null; world_name: string | null; 
+/**
+ * URL のホスト名 (URL として解釈できなければ URL そのもの)
+ */
+host: string; 
+/**
+ * 期間内に読み込んだ回数
+ */
+loads: number; first_seen: string; last_seen: string }
+
 
 /** tauri-specta globals **/
 
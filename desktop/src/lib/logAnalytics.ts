@@ -7,6 +7,13 @@ export interface PlayerInterval {
   totalDurationMs: number;
 }
 
+export interface VideoEntry {
+  url: string;
+  resolvedUrl: string | null;
+  requestedBy: string | null;
+  time: number;
+}
+
 export interface WorldSession {
   worldName: string;
   instanceId: string;
@@ -15,6 +22,7 @@ export interface WorldSession {
   durationMs: number;
   username: string | null;
   players: PlayerInterval[];
+  videos: VideoEntry[];
 }
 
 /**
@@ -33,6 +41,8 @@ export function analyzeSessions(logs: Payload[]): WorldSession[] {
   let activePlayers = new Map<string, { name: string; start: number }>();
   // 完了したプレイヤーの区間記録 (id -> [{name, start, end }, ...])
   let playerIntervals = new Map<string, { name: string; start: number; end: number }[]>();
+  // セッション中に再生された動画
+  let videos: VideoEntry[] = [];
 
   // ヘルパー: 日付文字列をタイムスタンプに変換 (ハイフン区切り対応)
   const toTime = (ts: string) => new Date(ts.replace(' ', 'T')).getTime();
@@ -77,12 +87,14 @@ export function analyzeSessions(logs: Payload[]): WorldSession[] {
       durationMs: currentSession.endTime - currentSession.startTime,
       username: me ? me.name : null,
       players: players.sort((a, b) => b.totalDurationMs - a.totalDurationMs), // 長くいた順
+      videos,
     });
 
     // リセット
     currentSession = null;
     activePlayers.clear();
     playerIntervals.clear();
+    videos = [];
   };
 
   logs.forEach((log) => {
@@ -131,6 +143,22 @@ export function analyzeSessions(logs: Payload[]): WorldSession[] {
         activePlayers.delete(data.user_id);
       }
     }
+    // 動画の再生
+    // 以前のバージョンでは1回の再生が複数行 (解決開始・解決結果など) に分けて記録されていたので、直前と同じURLならまとめる
+    else if (type === "VideoPlay" && currentSession) {
+      const last = videos[videos.length - 1];
+      if (last && (last.url === data.url || last.resolvedUrl === data.url)) {
+        last.resolvedUrl = last.resolvedUrl ?? data.resolved_url;
+        last.requestedBy = last.requestedBy ?? data.requested_by;
+      } else {
+        videos.push({
+          url: data.url,
+          resolvedUrl: data.resolved_url,
+          requestedBy: data.requested_by,
+          time: ts,
+        });
+      }
+    }
     // 4. アプリ終了など (セッション終了)
     else if (type === "AppStop" && currentSession) {
       closeSession(ts);
@@ -1,7 +1,10 @@
 import { useEffect, useState, useMemo } from "react";
-import { commands } from "../generated/bindings";
+import { commands, type Payload } from "../generated/bindings";
 import { analyzeSessions, type WorldSession } from "../lib/logAnalytics";
-import { Calendar, ChevronLeft, ChevronRight, LayoutList, BarChart3, Clock, MapPin, User, Hash, Users, Globe } from "lucide-react";
+import { Calendar, ChevronLeft, ChevronRight, LayoutList, BarChart3, Clock, MapPin, User, Hash, Users, Globe, Film } from "lucide-react";
+
+// getLogs で1回に取得する件数
+const PAGE_SIZE = 1000;
 
 export default function History() {
   const [targetDate, setTargetDate] = useState(new Date().toISOString().split('T')[0]); // YYYY-MM-DD
@@ -23,14 +26,21 @@ export default function History() {
       const start = `${dateStr} 00:00:00`;
       const end = `${dateStr} 23:59:59`;
 
-      const result = await commands.getLogs(start, end);
-
-      if (result.status === "ok") {
-        const parsedSessions = analyzeSessions(result.data);
-        setSessions(parsedSessions);
-      } else {
-        console.error(result.error);
-      }
+      // ページ単位で全件取得
+      const logs: Payload[] = [];
+      let cursor: string | null = null;
+      do {
+        const result = await commands.getLogs(start, end, null, PAGE_SIZE, cursor);
+        if (result.status !== "ok") {
+          console.error(result.error);
+          return;
+        }
+        logs.push(...result.data.items);
+        cursor = result.data.next_cursor;
+      } while (cursor);
+
+      const parsedSessions = analyzeSessions(logs);
+      setSessions(parsedSessions);
     } catch (e) {
       console.error(e);
     } finally {
@@ -415,6 +425,24 @@ function SessionCard({ session }: { session: WorldSession }) {
         {session.players.length === 0 && (
           <p className="text-xs text-slate-600 italic">No other players detected during this session.</p>
         )}
+
+        {/* 再生された動画 */}
+        {session.videos.length > 0 && (
+          <div className="space-y-1 mt-4 border-t border-slate-700/50 pt-2">
+            <div className="text-[10px] text-slate-500 uppercase tracking-wider mb-2 flex items-center gap-1">
+              <Film size={12} /> Videos ({session.videos.length})
+            </div>
+            {session.videos.map((video, i) => (
+              <div key={i} className="flex items-center gap-3 text-xs text-slate-300">
+                <span className="text-slate-500 font-mono text-[10px] shrink-0">{formatTime(video.time)}</span>
+                <span className="truncate flex-1" title={video.resolvedUrl ?? video.url}>{video.url}</span>
+                {video.requestedBy && (
+                  <span className="text-[10px] text-slate-500 shrink-0">by {video.requestedBy}</span>
+                )}
+              </div>
+            ))}
+          </div>
+        )}
       </div>
     </div>
   );
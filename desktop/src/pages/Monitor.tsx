@@ -20,6 +20,10 @@ export default function Monitor() {
       case "InstanceJoin": return { color: "text-orange-400", text: `Instance: ${event.data.instance_id}` };
       case "PlayerJoin": return { color: "text-cyan-400", text: `[+] ${event.data.player_name}` };
       case "PlayerLeft": return { color: "text-gray-400", text: `[-] ${event.data.player_name}` };
+      case "AvatarChange": return { color: "text-purple-400", text: `${event.data.player_name} → ${event.data.avatar_name}` };
+      case "Screenshot": return { color: "text-emerald-400", text: `Photo: ${event.data.path}` };
+      case "RemoteContentLoad": return { color: "text-amber-400", text: `${event.data.kind}: ${event.data.url}` };
+      case "VideoPlay": return { color: "text-pink-400", text: `Video: ${event.data.url}${event.data.requested_by ? ` (by ${event.data.requested_by})` : ""}` };
       default: return { color: "text-white", text: JSON.stringify(event) };
     }
   };
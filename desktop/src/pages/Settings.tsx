@@ -1,10 +1,10 @@
 import { useState, useEffect } from "react";
 import QRCode from "react-qr-code";
 import { enable, disable, isEnabled } from "@tauri-apps/plugin-autostart";
-import { commands } from "../generated/bindings";
+import { commands, type PairingInfo, type BindMode, type NetworkAddress, type ForwardConfig, type ForwardStatus } from "../generated/bindings";
 import { useLogContext } from "../context/LogContext";
-import { save, ask } from "@tauri-apps/plugin-dialog";
-import { Smartphone, Power, Globe, Database, Download, Trash2, AlertTriangle } from "lucide-react";
+import { save, ask, open } from "@tauri-apps/plugin-dialog";
+import { Smartphone, Power, Globe, Database, Download, Trash2, AlertTriangle, RefreshCw, ShieldOff, Lock, Send, Camera, FolderInput, Film } from "lucide-react";
 
 export default function Settings() {
   const { serverUrl } = useLogContext();
@@ -12,12 +12,62 @@ export default function Settings() {
   const [portInput, setPortInput] = useState<string | null>(null);
 
   const [isProcessing, setIsProcessing] = useState(false);
+  const [pairing, setPairing] = useState<PairingInfo | null>(null);
+  const [bindMode, setBindMode] = useState<BindMode | null>(null);
+  const [addresses, setAddresses] = useState<NetworkAddress[]>([]);
+  const [advertisedAddress, setAdvertisedAddress] = useState<string | null>(null);
+  const [tlsEnabled, setTlsEnabled] = useState<boolean | null>(null);
+  const [forwardConfig, setForwardConfig] = useState<ForwardConfig | null>(null);
+  const [forwardStatus, setForwardStatus] = useState<ForwardStatus | null>(null);
+  const [embedMetadata, setEmbedMetadata] = useState<boolean | null>(null);
 
   useEffect(() => {
     // 自動起動設定の確認
     isEnabled().then(setAutoStart).catch(console.error);
+    // 待ち受け設定とインターフェース一覧
+    commands.getBindMode().then((result) => {
+      if (result.status === "ok") setBindMode(result.data);
+    }).catch(console.error);
+    commands.listNetworkAddresses().then((result) => {
+      if (result.status === "ok") setAddresses(result.data.filter((a) => !a.is_loopback));
+    }).catch(console.error);
+    commands.getAdvertisedAddress().then((result) => {
+      if (result.status === "ok") setAdvertisedAddress(result.data);
+    }).catch(console.error);
+    commands.getTlsEnabled().then((result) => {
+      if (result.status === "ok") setTlsEnabled(result.data);
+    }).catch(console.error);
+    commands.getForwardConfig().then((result) => {
+      if (result.status === "ok") setForwardConfig(result.data);
+    }).catch(console.error);
+    commands.getEmbedScreenshotMetadata().then((result) => {
+      if (result.status === "ok") setEmbedMetadata(result.data);
+    }).catch(console.error);
   }, []);
 
+  // 転送の状態 (送信待ちの件数など) を定期的に更新
+  useEffect(() => {
+    const refreshStatus = () => {
+      commands.getForwardStatus().then((result) => {
+        if (result.status === "ok") setForwardStatus(result.data);
+      }).catch(console.error);
+    };
+    refreshStatus();
+    const timer = setInterval(refreshStatus, 5 * 1000);
+    return () => clearInterval(timer);
+  }, []);
+
+  // ペアリング情報 (QRコードにURLとトークンを埋め込む)
+  useEffect(() => {
+    commands.getPairingInfo().then((result) => {
+      if (result.status === "ok") {
+        setPairing(result.data);
+      } else {
+        console.error("Failed to get pairing info:", result.error);
+      }
+    }).catch(console.error);
+  }, [serverUrl]);
+
   // serverUrl (例: http://192.168.1.5:8727) がロードされたら、そこからポート番号を抽出して入力欄に反映
   useEffect(() => {
     if (serverUrl) {
@@ -51,14 +101,142 @@ export default function Settings() {
     }
 
     try {
-      await commands.setServerPort(portNum);
-      alert("ポート設定を保存しました。\n反映するにはアプリを再起動してください。");
+      const result = await commands.setServerPort(portNum);
+      if (result.status === "ok") {
+        alert("ポート設定を保存しました。");
+      } else {
+        alert(`保存に失敗しました: ${result.error}`);
+      }
     } catch (e) {
       console.error(e);
       alert(`保存に失敗しました: ${e}`);
     }
   };
 
+  const handleBindModeChange = async (value: string) => {
+    const mode: BindMode =
+      value === "Localhost" ? { mode: "Localhost" }
+        : value === "Lan" ? { mode: "Lan" }
+          : { mode: "Interface", address: value };
+
+    const result = await commands.setBindMode(mode);
+    if (result.status === "ok") {
+      setBindMode(mode);
+      const pairingResult = await commands.getPairingInfo();
+      if (pairingResult.status === "ok") setPairing(pairingResult.data);
+    } else {
+      alert(`Failed to change listening address: ${result.error}`);
+    }
+  };
+
+  const handleAdvertisedAddressChange = async (value: string) => {
+    const address = value === "" ? null : value;
+    const result = await commands.setAdvertisedAddress(address);
+    if (result.status === "ok") {
+      setAdvertisedAddress(address);
+      const pairingResult = await commands.getPairingInfo();
+      if (pairingResult.status === "ok") setPairing(pairingResult.data);
+    } else {
+      alert(`Failed to change the address in the QR code: ${result.error}`);
+    }
+  };
+
+  const toggleTls = async () => {
+    if (tlsEnabled === null) return;
+    const result = await commands.setTlsEnabled(!tlsEnabled);
+    if (result.status === "ok") {
+      setTlsEnabled(!tlsEnabled);
+      const pairingResult = await commands.getPairingInfo();
+      if (pairingResult.status === "ok") setPairing(pairingResult.data);
+    } else {
+      alert(`Failed to change HTTPS setting: ${result.error}`);
+    }
+  };
+
+  // 転送先のURL欄にペアリングリンク (vrcp://pair?...) が貼られたら各項目に展開する
+  const handleForwardUrlChange = (value: string) => {
+    if (!forwardConfig) return;
+    if (value.startsWith("vrcp://pair?")) {
+      const params = new URLSearchParams(value.slice("vrcp://pair?".length));
+      setForwardConfig({
+        ...forwardConfig,
+        url: params.get("url") ?? "",
+        token: params.get("token") ?? "",
+        fingerprint: params.get("fp"),
+      });
+    } else {
+      setForwardConfig({ ...forwardConfig, url: value });
+    }
+  };
+
+  const handleSaveForwarding = async () => {
+    if (!forwardConfig) return;
+    const result = await commands.setForwardConfig(forwardConfig);
+    if (result.status === "ok") {
+      alert("Forwarding settings saved.");
+    } else {
+      alert(`Failed to save forwarding settings: ${result.error}`);
+    }
+  };
+
+  const handleRotateToken = async () => {
+    const confirmed = await ask("Generate a new pairing code?\nPaired devices will need to scan the new QR code.", {
+      title: 'Regenerate Pairing Code',
+      kind: 'warning',
+    });
+    if (!confirmed) return;
+
+    const result = await commands.rotatePairingToken();
+    if (result.status === "ok") {
+      setPairing(result.data);
+    } else {
+      alert(`Failed to regenerate pairing code: ${result.error}`);
+    }
+  };
+
+  const handleRevokeToken = async () => {
+    const confirmed = await ask("Revoke access for all paired devices?\nNo device can connect until a new pairing code is generated.", {
+      title: 'Revoke Pairing',
+      kind: 'warning',
+    });
+    if (!confirmed) return;
+
+    const result = await commands.revokePairingToken();
+    if (result.status === "ok") {
+      setPairing((prev) => (prev ? { ...prev, token: null, qr_payload: null } : prev));
+    } else {
+      alert(`Failed to revoke pairing: ${result.error}`);
+    }
+  };
+
+  const toggleEmbedMetadata = async () => {
+    if (embedMetadata === null) return;
+    const result = await commands.setEmbedScreenshotMetadata(!embedMetadata);
+    if (result.status === "ok") {
+      setEmbedMetadata(!embedMetadata);
+    } else {
+      alert(`Failed to change screenshot setting: ${result.error}`);
+    }
+  };
+
+  const handleImportScreenshots = async () => {
+    // 写真のフォルダを選択 (サブフォルダも含めて取り込む)
+    const dir = await open({ directory: true });
+    if (!dir) return;
+
+    setIsProcessing(true);
+    try {
+      const result = await commands.importScreenshots(dir);
+      if (result.status === "ok") {
+        alert(`Import finished.\nImported ${result.data} screenshots.`);
+      } else {
+        alert(`Import failed: ${result.error}`);
+      }
+    } finally {
+      setIsProcessing(false);
+    }
+  };
+
   const handleExport = async () => {
     try {
       // 1. 保存先ダイアログを表示
@@ -89,6 +267,29 @@ export default function Settings() {
     }
   };
 
+  const handleExportWatchList = async () => {
+    const filePath = await save({
+      filters: [{ name: 'JSON', extensions: ['json'] }],
+      defaultPath: 'vrcp_watch_list.json',
+    });
+    if (!filePath) return;
+
+    setIsProcessing(true);
+    try {
+      const result = await commands.exportWatchList(filePath);
+      if (result.status === "ok") {
+        alert(`Export successful!\nSaved ${result.data} videos.`);
+      } else {
+        alert(`Export failed: ${result.error}`);
+      }
+    } catch (e) {
+      console.error(e);
+      alert(`Export failed: ${e}`);
+    } finally {
+      setIsProcessing(false);
+    }
+  };
+
   const handleClear = async () => {
     // 1. 確認ダイアログ (Tauriのネイティブダイアログ推奨)
     const confirmed = await ask("Are you sure you want to delete ALL logs?\nThis action cannot be undone.", {
@@ -127,7 +328,15 @@ export default function Settings() {
           </h3>
           <div className="flex gap-8 items-start">
             <div className="bg-white p-2 rounded-lg shrink-0">
-              {serverUrl ? <QRCode value={serverUrl} size={120} /> : <div className="w-[120px] h-[120px] bg-gray-200 animate-pulse rounded" />}
+              {pairing?.qr_payload ? (
+                <QRCode value={pairing.qr_payload} size={120} />
+              ) : pairing ? (
+                <div className="w-[120px] h-[120px] bg-gray-200 rounded flex items-center justify-center text-slate-500">
+                  <ShieldOff size={32} />
+                </div>
+              ) : (
+                <div className="w-[120px] h-[120px] bg-gray-200 animate-pulse rounded" />
+              )}
             </div>
             <div>
               <p className="font-medium mb-1">Scan this QR Code</p>
@@ -136,8 +345,32 @@ export default function Settings() {
                 Ensure both devices are on the same Wi-Fi.
               </p>
               <code className="bg-slate-950 px-3 py-1 rounded text-xs font-mono text-slate-300 block w-fit">
-                {serverUrl || "Fetching IP..."}
+                {pairing?.url || serverUrl || "Fetching IP..."}
               </code>
+              {pairing?.fingerprint && (
+                <p className="text-xs text-slate-500 mt-2 flex items-center gap-1">
+                  <Lock size={12} /> Certificate: <span className="font-mono break-all">{pairing.fingerprint}</span>
+                </p>
+              )}
+              {pairing && !pairing.token && (
+                <p className="text-xs text-yellow-500 mt-2">Pairing is revoked. Generate a new code to connect.</p>
+              )}
+              <div className="flex gap-2 mt-4">
+                <button
+                  onClick={handleRotateToken}
+                  className="bg-slate-700 hover:bg-slate-600 px-3 py-1.5 rounded-lg transition text-sm flex items-center gap-2"
+                >
+                  <RefreshCw size={14} /> {pairing?.token ? "Regenerate Code" : "Generate Code"}
+                </button>
+                {pairing?.token && (
+                  <button
+                    onClick={handleRevokeToken}
+                    className="border border-red-500/30 text-red-400 hover:bg-red-500/10 px-3 py-1.5 rounded-lg transition text-sm flex items-center gap-2"
+                  >
+                    <ShieldOff size={14} /> Revoke
+                  </button>
+                )}
+              </div>
             </div>
           </div>
         </section>
@@ -171,7 +404,7 @@ export default function Settings() {
               <p className="font-medium">Server Port</p>
               <p className="text-sm text-slate-400">
                 Change the listening port for mobile connection. <br />
-                <span className="text-yellow-500 text-xs">Note: App restarts automatically to apply change.</span>
+                <span className="text-yellow-500 text-xs">Note: Connected devices need to reconnect after the change.</span>
               </p>
             </div>
             <div className="flex items-center gap-3">
@@ -192,6 +425,180 @@ export default function Settings() {
           </div>
         </section>
 
+        {/* Network Settings (Bind Address) */}
+        <section className="bg-slate-800/40 p-6 rounded-xl border border-slate-700 -mt-4">
+          <div className="flex items-center justify-between">
+            <div>
+              <p className="font-medium">Listening Address</p>
+              <p className="text-sm text-slate-400">
+                Choose which network the mobile app can connect from.
+              </p>
+            </div>
+            <select
+              value={bindMode ? (bindMode.mode === "Interface" ? bindMode.address : bindMode.mode) : ""}
+              onChange={(e) => handleBindModeChange(e.target.value)}
+              disabled={!bindMode}
+              className="bg-slate-900 border border-slate-600 rounded px-3 py-2 max-w-xs font-mono text-sm focus:outline-none focus:border-blue-500 transition"
+            >
+              <option value="Lan">All networks (LAN)</option>
+              <option value="Localhost">This PC only (localhost)</option>
+              {addresses.map((a) => (
+                <option key={`${a.interface}-${a.address}`} value={a.address}>
+                  {a.interface}: {a.address}
+                </option>
+              ))}
+            </select>
+          </div>
+          {bindMode?.mode === "Lan" && (
+            <div className="flex items-center justify-between mt-4">
+              <div>
+                <p className="font-medium">Address in QR Code</p>
+                <p className="text-sm text-slate-400">
+                  Pick the address the mobile app can reach if the automatic one is wrong (e.g. a VPN adapter).
+                </p>
+              </div>
+              <select
+                value={advertisedAddress ?? ""}
+                onChange={(e) => handleAdvertisedAddressChange(e.target.value)}
+                className="bg-slate-900 border border-slate-600 rounded px-3 py-2 max-w-xs font-mono text-sm focus:outline-none focus:border-blue-500 transition"
+              >
+                <option value="">Automatic</option>
+                {addresses.map((a) => (
+                  <option key={`${a.interface}-${a.address}`} value={a.address}>
+                    {a.interface}: {a.address}
+                  </option>
+                ))}
+              </select>
+            </div>
+          )}
+        </section>
+
+        {/* Network Settings (HTTPS) */}
+        <section className="bg-slate-800/40 p-6 rounded-xl border border-slate-700 -mt-4">
+          <div className="flex items-center justify-between">
+            <div>
+              <p className="font-medium">HTTPS</p>
+              <p className="text-sm text-slate-400">
+                Encrypt the connection with a self-signed certificate. <br />
+                <span className="text-yellow-500 text-xs">Note: Paired devices need to scan the QR code again after the change.</span>
+              </p>
+            </div>
+            <label className="relative inline-flex items-center cursor-pointer">
+              <input type="checkbox" className="sr-only peer" checked={tlsEnabled ?? false} disabled={tlsEnabled === null} onChange={toggleTls} />
+              <div className="w-11 h-6 bg-slate-700 peer-focus:outline-none rounded-full peer peer-checked:after:translate-x-full peer-checked:after:border-white after:content-[''] after:absolute after:top-[2px] after:left-[2px] after:bg-white after:border-gray-300 after:border after:rounded-full after:h-5 after:w-5 after:transition-all peer-checked:bg-blue-600"></div>
+            </label>
+          </div>
+        </section>
+
+        {/* Forwarding */}
+        <section className="bg-slate-800/40 p-6 rounded-xl border border-slate-700">
+          <h3 className="text-xl font-semibold mb-4 flex items-center gap-2">
+            <Send className="text-teal-400" /> Forwarding
+          </h3>
+          <div className="space-y-4">
+            <div className="flex items-center justify-between">
+              <div>
+                <p className="font-medium">Forward to another VRCP</p>
+                <p className="text-sm text-slate-400">
+                  Send events recorded on this PC to the VRCP on another PC.
+                </p>
+              </div>
+              <select
+                value={forwardConfig?.mode ?? "Off"}
+                onChange={(e) => forwardConfig && setForwardConfig({ ...forwardConfig, mode: e.target.value as ForwardConfig["mode"] })}
+                disabled={!forwardConfig}
+                className="bg-slate-900 border border-slate-600 rounded px-3 py-2 text-sm focus:outline-none focus:border-blue-500 transition"
+              >
+                <option value="Off">Off</option>
+                <option value="Mirror">Save here and forward</option>
+                <option value="ForwardOnly">Forward only</option>
+              </select>
+            </div>
+            {forwardConfig && forwardConfig.mode !== "Off" && (
+              <div className="grid gap-2">
+                <input
+                  type="text"
+                  value={forwardConfig.url}
+                  onChange={(e) => handleForwardUrlChange(e.target.value)}
+                  placeholder="https://192.168.1.5:8727 or pairing link (vrcp://pair?...)"
+                  className="bg-slate-900 border border-slate-600 rounded px-3 py-2 font-mono text-sm focus:outline-none focus:border-blue-500 transition"
+                />
+                <input
+                  type="password"
+                  value={forwardConfig.token}
+                  onChange={(e) => setForwardConfig({ ...forwardConfig, token: e.target.value })}
+                  placeholder="Pairing token"
+                  className="bg-slate-900 border border-slate-600 rounded px-3 py-2 font-mono text-sm focus:outline-none focus:border-blue-500 transition"
+                />
+                <input
+                  type="text"
+                  value={forwardConfig.fingerprint ?? ""}
+                  onChange={(e) => setForwardConfig({ ...forwardConfig, fingerprint: e.target.value || null })}
+                  placeholder="Certificate fingerprint (required for https)"
+                  className="bg-slate-900 border border-slate-600 rounded px-3 py-2 font-mono text-xs focus:outline-none focus:border-blue-500 transition"
+                />
+              </div>
+            )}
+            <div className="flex items-center justify-between">
+              <p className="text-xs text-slate-400">
+                {forwardStatus
+                  ? `${forwardStatus.pending_events} events waiting` +
+                    (forwardStatus.last_success_at ? ` · last sent ${new Date(forwardStatus.last_success_at).toLocaleString()}` : "") +
+                    (forwardStatus.discarded_events > 0 ? ` · ${forwardStatus.discarded_events} discarded` : "")
+                  : ""}
+                {forwardStatus?.last_error && (
+                  <span className="block text-yellow-500">{forwardStatus.last_error}</span>
+                )}
+              </p>
+              <button
+                onClick={handleSaveForwarding}
+                disabled={!forwardConfig}
+                className="bg-blue-600 hover:bg-blue-500 text-white px-4 py-2 rounded-lg transition shadow-lg shadow-blue-900/20 font-medium disabled:opacity-50"
+              >
+                Save
+              </button>
+            </div>
+          </div>
+        </section>
+
+        {/* Screenshots */}
+        <section className="bg-slate-800/40 p-6 rounded-xl border border-slate-700">
+          <h3 className="text-xl font-semibold mb-4 flex items-center gap-2">
+            <Camera className="text-emerald-400" /> Screenshots
+          </h3>
+          <div className="space-y-6">
+            <div className="flex items-center justify-between pb-4 border-b border-slate-700/50">
+              <div>
+                <p className="font-medium">Embed metadata</p>
+                <p className="text-sm text-slate-400">
+                  Write the world, instance and players present into new VRChat screenshots (PNG).
+                  <br />
+                  <span className="text-yellow-500 text-xs">Note: Photos taken while VRCP was not running are updated when their logs are read at startup. Photos are not changed while forwarding only, because the events are stored on the other PC.</span>
+                </p>
+              </div>
+              <label className="relative inline-flex items-center cursor-pointer">
+                <input type="checkbox" className="sr-only peer" checked={embedMetadata ?? false} disabled={embedMetadata === null} onChange={toggleEmbedMetadata} />
+                <div className="w-11 h-6 bg-slate-700 peer-focus:outline-none rounded-full peer peer-checked:after:translate-x-full peer-checked:after:border-white after:content-[''] after:absolute after:top-[2px] after:left-[2px] after:bg-white after:border-gray-300 after:border after:rounded-full after:h-5 after:w-5 after:transition-all peer-checked:bg-blue-600"></div>
+              </label>
+            </div>
+            <div className="flex items-center justify-between">
+              <div>
+                <p className="font-medium flex items-center gap-2">
+                  <FolderInput size={18} className="text-blue-400" /> Import Screenshots
+                </p>
+                <p className="text-sm text-slate-400">Read the embedded metadata from a folder of screenshots into the database.</p>
+              </div>
+              <button
+                onClick={handleImportScreenshots}
+                disabled={isProcessing}
+                className="bg-slate-700 hover:bg-slate-600 px-4 py-2 rounded-lg transition disabled:opacity-50 flex items-center gap-2"
+              >
+                {isProcessing ? "Processing..." : "Choose Folder"}
+              </button>
+            </div>
+          </div>
+        </section>
+
         {/* Data Management Section */}
         <section className="bg-slate-800/40 p-6 rounded-xl border border-slate-700">
           <h3 className="text-xl font-semibold mb-4 flex items-center gap-2">
@@ -216,6 +623,23 @@ export default function Settings() {
               </button>
             </div>
 
+            {/* Export Watch List */}
+            <div className="flex items-center justify-between pb-4 border-b border-slate-700/50">
+              <div>
+                <p className="font-medium flex items-center gap-2">
+                  <Film size={18} className="text-blue-400" /> Export Watch List
+                </p>
+                <p className="text-sm text-slate-400">Save the videos played in each world to a JSON file.</p>
+              </div>
+              <button
+                onClick={handleExportWatchList}
+                disabled={isProcessing}
+                className="bg-slate-700 hover:bg-slate-600 px-4 py-2 rounded-lg transition disabled:opacity-50 flex items-center gap-2"
+              >
+                {isProcessing ? "Processing..." : "Export JSON"}
+              </button>
+            </div>
+
             {/* Clear */}
             <div className="flex items-center justify-between">
               <div>